@@ -0,0 +1,296 @@
+//! Append-only log of task changes, one JSON object per line.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::TodoItem;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Edited,
+    Completed,
+    Uncompleted,
+    Deleted,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChangeEvent {
+    pub time: NaiveDateTime,
+    pub task_id: usize,
+    pub event: ChangeKind,
+    // Field name -> `{"from": old, "to": new}`; only `to` for created tasks, empty for deleted ones
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub changes: Map<String, Value>,
+}
+
+impl ChangeEvent {
+    /// `2025-08-01 14:30 edited: title "a" -> "b", priority "Low" -> "High"`
+    pub fn summary(&self) -> String {
+        let event = match self.event {
+            ChangeKind::Created => "created",
+            ChangeKind::Edited => "edited",
+            ChangeKind::Completed => "completed",
+            ChangeKind::Uncompleted => "uncompleted",
+            ChangeKind::Deleted => "deleted",
+        };
+        let mut line = format!("{} {}", self.time.format("%Y-%m-%d %H:%M"), event);
+        // The full task at creation would drown the rest of the history
+        if self.event != ChangeKind::Created && !self.changes.is_empty() {
+            let fields: Vec<String> = self
+                .changes
+                .iter()
+                .map(|(field, change)| match (change.get("from"), change.get("to")) {
+                    (Some(from), Some(to)) => format!("{} {} -> {}", field, from, to),
+                    _ => field.clone(),
+                })
+                .collect();
+            line.push_str(": ");
+            line.push_str(&fields.join(", "));
+        }
+        line
+    }
+}
+
+/// `todos.json` -> `todos.log.jsonl`
+pub fn log_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".log.jsonl");
+    save_path.with_file_name(name)
+}
+
+/// Events turning the task list `before` into `after`, matching tasks by id
+pub fn diff(before: &[TodoItem], after: &[TodoItem], time: NaiveDateTime) -> Vec<ChangeEvent> {
+    let before: HashMap<usize, &TodoItem> = before.iter().map(|item| (item.id, item)).collect();
+    let after_ids: HashMap<usize, &TodoItem> = after.iter().map(|item| (item.id, item)).collect();
+    let mut events = Vec::new();
+
+    for item in after {
+        let Some(old) = before.get(&item.id) else {
+            let changes = fields(item)
+                .into_iter()
+                .map(|(field, to)| (field, serde_json::json!({ "to": to })))
+                .collect();
+            events.push(ChangeEvent {
+                time,
+                task_id: item.id,
+                event: ChangeKind::Created,
+                changes,
+            });
+            continue;
+        };
+        let old_fields = fields(old);
+        let changes: Map<String, Value> = fields(item)
+            .into_iter()
+            .filter(|(field, to)| old_fields.get(field) != Some(to))
+            .map(|(field, to)| {
+                let from = old_fields.get(&field).cloned().unwrap_or(Value::Null);
+                (field, serde_json::json!({ "from": from, "to": to }))
+            })
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        let event = match (old.completed, item.completed) {
+            (false, true) => ChangeKind::Completed,
+            (true, false) => ChangeKind::Uncompleted,
+            _ => ChangeKind::Edited,
+        };
+        events.push(ChangeEvent {
+            time,
+            task_id: item.id,
+            event,
+            changes,
+        });
+    }
+
+    let mut deleted: Vec<usize> = before
+        .keys()
+        .filter(|id| !after_ids.contains_key(id))
+        .copied()
+        .collect();
+    deleted.sort_unstable();
+    events.extend(deleted.into_iter().map(|task_id| ChangeEvent {
+        time,
+        task_id,
+        event: ChangeKind::Deleted,
+        changes: Map::new(),
+    }));
+    events
+}
+
+fn fields(item: &TodoItem) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(mut fields)) => {
+            fields.remove("id");
+            // Changes along with every other field; the event's time already says when
+            fields.remove("modified");
+            fields
+        }
+        _ => Map::new(),
+    }
+}
+
+pub fn append(path: &Path, events: &[ChangeEvent]) -> io::Result<()> {
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event)?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(lines.as_bytes())
+}
+
+/// Logged events for one task, oldest first. Lines that don't parse are skipped.
+pub fn history(path: &Path, task_id: usize) -> io::Result<Vec<ChangeEvent>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ChangeEvent>(line).ok())
+        .filter(|event| event.task_id == task_id)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use chrono::{NaiveDate, NaiveTime};
+    use serde_json::json;
+
+    fn time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 8, 1).unwrap().and_hms_opt(14, 30, 0).unwrap()
+    }
+
+    fn task(id: usize, title: &str) -> TodoItem {
+        let due = NaiveDate::from_ymd_opt(2025, 8, 31).unwrap().and_time(NaiveTime::MIN);
+        TodoItem::new(id, title.to_string(), String::new(), due)
+    }
+
+    /// The one event `diff` finds between `before` and `after`
+    fn only_event(before: &[TodoItem], after: &[TodoItem]) -> ChangeEvent {
+        let mut events = diff(before, after, time());
+        assert_eq!(events.len(), 1);
+        events.remove(0)
+    }
+
+    #[test]
+    fn created_task_lists_every_field_as_to() {
+        let event = only_event(&[], &[task(3, "Buy milk")]);
+
+        assert!(event.event == ChangeKind::Created);
+        assert_eq!(event.task_id, 3);
+        assert_eq!(event.time, time());
+        assert_eq!(event.changes["title"], json!({ "to": "Buy milk" }));
+        assert_eq!(event.changes["completed"], json!({ "to": false }));
+        assert!(!event.changes.contains_key("id"));
+        assert!(!event.changes.contains_key("modified"));
+    }
+
+    #[test]
+    fn edited_task_lists_only_the_changed_fields() {
+        let before = task(1, "Buy milk");
+        let mut after = before.clone();
+        after.title = "Buy oat milk".to_string();
+        after.tags = vec!["shop".to_string()];
+
+        let event = only_event(&[before], &[after]);
+
+        assert!(event.event == ChangeKind::Edited);
+        assert_eq!(event.changes.len(), 2);
+        assert_eq!(event.changes["title"], json!({ "from": "Buy milk", "to": "Buy oat milk" }));
+        assert_eq!(event.changes["tags"], json!({ "from": [], "to": ["shop"] }));
+    }
+
+    #[test]
+    fn completing_and_uncompleting() {
+        let pending = task(1, "Buy milk");
+        let mut done = pending.clone();
+        done.completed = true;
+        done.completed_date = NaiveDate::from_ymd_opt(2025, 8, 1);
+
+        let completed = only_event(std::slice::from_ref(&pending), std::slice::from_ref(&done));
+        assert!(completed.event == ChangeKind::Completed);
+        assert_eq!(completed.changes["completed"], json!({ "from": false, "to": true }));
+        assert_eq!(
+            completed.changes["completed_date"],
+            json!({ "from": null, "to": "2025-08-01" })
+        );
+
+        let uncompleted = only_event(&[done], &[pending]);
+        assert!(uncompleted.event == ChangeKind::Uncompleted);
+        assert_eq!(uncompleted.changes["completed"], json!({ "from": true, "to": false }));
+    }
+
+    #[test]
+    fn deleted_tasks_come_last_in_id_order() {
+        let before = [task(5, "e"), task(2, "b"), task(7, "g")];
+        let after = [task(7, "g"), task(8, "h")];
+
+        let events = diff(&before, &after, time());
+
+        let kinds: Vec<(usize, bool)> = events
+            .iter()
+            .map(|event| (event.task_id, event.event == ChangeKind::Deleted))
+            .collect();
+        assert_eq!(kinds, [(8, false), (2, true), (5, true)]);
+        assert!(events[1].changes.is_empty());
+    }
+
+    #[test]
+    fn modified_timestamp_alone_is_no_change() {
+        let before = task(1, "Buy milk");
+        let mut after = before.clone();
+        after.modified = Some(time());
+
+        assert!(diff(&[before], &[after], time()).is_empty());
+    }
+
+    #[test]
+    fn history_filters_by_task_and_skips_bad_lines() {
+        let dir = TempDir::new("changelog-history");
+        let path = dir.join("todos.log.jsonl");
+        let mut after = vec![task(1, "Buy milk"), task(2, "Call mom")];
+        append(&path, &diff(&[], &after, time())).unwrap();
+        fs::write(
+            &path,
+            fs::read_to_string(&path).unwrap() + "not json\n{\"task_id\": 1}\n\n",
+        )
+        .unwrap();
+        let before = after.clone();
+        after[0].completed = true;
+        append(&path, &diff(&before, &after, time())).unwrap();
+
+        let events = history(&path, 1).unwrap();
+
+        let kinds: Vec<bool> =
+            events.iter().map(|event| event.event == ChangeKind::Completed).collect();
+        assert_eq!(kinds, [false, true]);
+        assert!(events.iter().all(|event| event.task_id == 1));
+        assert_eq!(history(&path, 2).unwrap().len(), 1);
+        assert!(history(&path, 3).unwrap().is_empty());
+        assert!(history(&dir.join("missing.log.jsonl"), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn summary_line() {
+        let before = task(1, "a");
+        let mut after = before.clone();
+        after.title = "b".to_string();
+
+        assert_eq!(
+            only_event(&[before], &[after]).summary(),
+            r#"2025-08-01 14:30 edited: title "a" -> "b""#
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! System clipboard access through the platform's command-line tools, so no display server
+//! libraries are linked in. Every function quietly gives up when no tool is available.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Readers tried in order: macOS, Wayland, X11 (two common tools), Windows
+const PASTE_COMMANDS: [&[&str]; 5] = [
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+    &["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+const COPY_COMMANDS: [&[&str]; 5] = [
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["clip"],
+];
+
+/// Text on the clipboard, or `None` if it can't be read
+pub fn paste() -> Option<String> {
+    PASTE_COMMANDS.iter().find_map(|command| {
+        let output = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let text = String::from_utf8(output.stdout).ok()?;
+        // Get-Clipboard ends its output with a line break that isn't part of the text
+        Some(text.strip_suffix("\r\n").map(str::to_string).unwrap_or(text))
+    })
+}
+
+/// Puts `text` on the clipboard. Without a clipboard tool that takes it this falls back to
+/// the OSC 52 escape sequence, which many terminals (also over SSH) turn into a clipboard
+/// write.
+pub fn copy(text: &str) -> io::Result<()> {
+    if COPY_COMMANDS.iter().any(|command| copy_with(command, text)) {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+/// Whether `command` took all of `text` and exited successfully. A tool that closes its
+/// input early (a broken pipe) or can't be waited on counts as a failure like any other,
+/// so the caller moves on to the next one.
+fn copy_with(command: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    // Dropping stdin closes the pipe, so the tool sees the end of the text
+    let written =
+        child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    let exited = child.wait().is_ok_and(|status| status.success());
+    written && exited
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_with_a_tool_that_reads_everything() {
+        assert!(copy_with(&["cat"], "hello"));
+    }
+
+    #[test]
+    fn copy_with_a_missing_or_failing_tool() {
+        assert!(!copy_with(&["rtodo-no-such-clipboard-tool"], "hello"));
+        assert!(!copy_with(&["sh", "-c", "cat >/dev/null; exit 1"], "hello"));
+    }
+
+    #[test]
+    fn copy_with_a_tool_that_closes_its_input_early() {
+        // More than a pipe buffer, so the write hits the closed pipe
+        let text = "x".repeat(1 << 20);
+        assert!(!copy_with(&["true"], &text));
+    }
+
+    #[test]
+    fn base64_pads_to_whole_quads() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,40 @@
+//! User preferences shared by every list, read from `~/.config/rtodo/config.toml` (or the
+//! platform's equivalent).
+
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+use crate::theme::Theme;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Open tasks due within this many days are highlighted before they go overdue
+    pub warn_days: u32,
+    /// Colors to draw with; set from `--theme` or `theme.toml`, not from this file
+    #[serde(skip)]
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { warn_days: 3, theme: Theme::default() }
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rtodo").map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+/// The config at `path`, or the defaults when there is no such file
+pub fn load(path: &Path) -> Result<Config, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => toml::from_str(&content).map_err(|err| err.to_string()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err.to_string()),
+    }
+}
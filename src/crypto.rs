@@ -0,0 +1,263 @@
+//! Passphrase encryption of the save file.
+//!
+//! An encrypted file is plain text so it fits the rest of the save path:
+//!
+//! ```text
+//! rtodo-encrypted v1
+//! salt <hex>
+//! nonce <hex>
+//! <hex ciphertext>
+//! ```
+//!
+//! The key is derived from the passphrase and the salt with Argon2id, and the save file
+//! content is sealed with ChaCha20-Poly1305 under a fresh nonce on every save.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, Generate, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use std::fmt;
+
+const HEADER: &str = "rtodo-encrypted v1";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+pub enum DecryptError {
+    /// Authentication failed: the passphrase is wrong (or the ciphertext was altered)
+    WrongPassphrase,
+    /// The header or encoding is damaged
+    Invalid(String),
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptError::WrongPassphrase => f.write_str("wrong passphrase"),
+            DecryptError::Invalid(err) => write!(f, "damaged encrypted file: {}", err),
+        }
+    }
+}
+
+/// A passphrase together with the key derived from it for one salt
+#[derive(Clone)]
+pub struct Cipher {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    key: [u8; KEY_LEN],
+}
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(HEADER)
+}
+
+impl Cipher {
+    /// Sets up encryption of a file that isn't encrypted yet, under a new random salt
+    pub fn new(passphrase: &str) -> Result<Self, String> {
+        let salt = <[u8; SALT_LEN]>::try_generate().map_err(|err| err.to_string())?;
+        Self::with_salt(passphrase, salt)
+    }
+
+    /// Derives the key for an encrypted file's salt. Slow on purpose, so callers keep the
+    /// result around rather than calling this per save.
+    pub fn for_file(passphrase: &str, content: &str) -> Result<Self, DecryptError> {
+        let salt = parse(content)?.salt;
+        Self::with_salt(passphrase, salt).map_err(DecryptError::Invalid)
+    }
+
+    fn with_salt(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, String> {
+        let mut key = [0; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            passphrase: passphrase.to_string(),
+            salt,
+            key,
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let nonce = Nonce::try_generate().map_err(|err| err.to_string())?;
+        let ciphertext = self
+            .aead()
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|err| err.to_string())?;
+        Ok(format!(
+            "{}\nsalt {}\nnonce {}\n{}\n",
+            HEADER,
+            to_hex(&self.salt),
+            to_hex(&nonce),
+            to_hex(&ciphertext)
+        ))
+    }
+
+    /// Decrypts `content`, deriving a new key first if it was encrypted under another salt
+    /// (e.g. a backup from before the file was re-encrypted)
+    pub fn decrypt(&self, content: &str) -> Result<String, DecryptError> {
+        let sealed = parse(content)?;
+        if sealed.salt != self.salt {
+            return Self::with_salt(&self.passphrase, sealed.salt)
+                .map_err(DecryptError::Invalid)?
+                .decrypt(content);
+        }
+        let nonce = Nonce::try_from(sealed.nonce.as_slice())
+            .map_err(|_| DecryptError::Invalid("bad nonce length".to_string()))?;
+        let plaintext = self
+            .aead()
+            .decrypt(&nonce, sealed.ciphertext.as_slice())
+            .map_err(|_| DecryptError::WrongPassphrase)?;
+        String::from_utf8(plaintext).map_err(|err| DecryptError::Invalid(err.to_string()))
+    }
+
+    fn aead(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&self.key.into())
+    }
+}
+
+struct Sealed {
+    salt: [u8; SALT_LEN],
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+fn parse(content: &str) -> Result<Sealed, DecryptError> {
+    let invalid = |what: &str| DecryptError::Invalid(what.to_string());
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(invalid("missing header"));
+    }
+    let mut field = |name: &str| {
+        lines
+            .next()
+            .and_then(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .and_then(from_hex)
+            .ok_or_else(|| invalid(&format!("bad {} line", name)))
+    };
+    let salt = field("salt")?
+        .try_into()
+        .map_err(|_| invalid("bad salt length"))?;
+    let nonce = field("nonce")?;
+    let ciphertext = lines
+        .next()
+        .and_then(from_hex)
+        .ok_or_else(|| invalid("bad ciphertext"))?;
+    Ok(Sealed {
+        salt,
+        nonce,
+        ciphertext,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = r#"{"version": 3, "items": [{"id": 1, "title": "Call Globex"}]}"#;
+
+    fn cipher(passphrase: &str) -> Cipher {
+        Cipher::new(passphrase).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn decrypt(cipher: &Cipher, content: &str) -> String {
+        cipher.decrypt(content).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[test]
+    fn round_trip_through_the_file_header() {
+        let sealed = cipher("correct horse").encrypt(LIST).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("Globex"));
+
+        let Ok(reader) = Cipher::for_file("correct horse", &sealed) else {
+            panic!("key derivation failed");
+        };
+        assert_eq!(decrypt(&reader, &sealed), LIST);
+    }
+
+    #[test]
+    fn every_save_gets_a_fresh_nonce() {
+        let cipher = cipher("correct horse");
+        let first = cipher.encrypt(LIST).unwrap();
+        let second = cipher.encrypt(LIST).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(decrypt(&cipher, &first), decrypt(&cipher, &second));
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let sealed = cipher("correct horse").encrypt(LIST).unwrap();
+        let Ok(reader) = Cipher::for_file("battery staple", &sealed) else {
+            panic!("key derivation failed");
+        };
+
+        assert!(matches!(reader.decrypt(&sealed), Err(DecryptError::WrongPassphrase)));
+    }
+
+    #[test]
+    fn file_under_another_salt_derives_its_own_key() {
+        let old = cipher("correct horse").encrypt(LIST).unwrap();
+        let current = cipher("correct horse");
+        assert_ne!(parse(&old).ok().map(|sealed| sealed.salt), Some(current.salt));
+
+        assert_eq!(decrypt(&current, &old), LIST);
+        assert!(matches!(
+            cipher("battery staple").decrypt(&old),
+            Err(DecryptError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn damaged_header_is_invalid() {
+        let cipher = cipher("correct horse");
+        let sealed = cipher.encrypt(LIST).unwrap();
+        let lines: Vec<&str> = sealed.lines().collect();
+        let damaged = [
+            String::new(),
+            LIST.to_string(),
+            lines[..1].join("\n"),
+            lines[..2].join("\n"),
+            lines[..3].join("\n"),
+            sealed.replacen("salt ", "salt 00", 1),
+            sealed.replacen("salt ", "salt 0", 1),
+            sealed.replacen("nonce ", "nonce 0000", 1),
+            sealed.replacen("nonce ", "nonce zz", 1),
+            sealed.replacen("rtodo-encrypted v1", "rtodo-encrypted v2", 1),
+        ];
+        for content in damaged {
+            assert!(
+                matches!(cipher.decrypt(&content), Err(DecryptError::Invalid(_))),
+                "{:?}",
+                content
+            );
+        }
+        assert!(matches!(
+            Cipher::for_file("correct horse", &lines[..2].join("\n")),
+            Err(DecryptError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn altered_ciphertext_fails_authentication() {
+        let cipher = cipher("correct horse");
+        let sealed = cipher.encrypt(LIST).unwrap();
+        let last = sealed.trim_end().len() - 1;
+        let flipped = if &sealed[last..=last] == "0" { "1" } else { "0" };
+        let altered = format!("{}{}\n", &sealed[..last], flipped);
+
+        assert!(matches!(cipher.decrypt(&altered), Err(DecryptError::WrongPassphrase)));
+    }
+}
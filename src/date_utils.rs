@@ -0,0 +1,128 @@
+//! Parsing and formatting of the deadlines typed into the task form.
+
+use chrono::{Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// Time given to deadlines entered without one
+pub const END_OF_DAY: NaiveTime = match NaiveTime::from_hms_opt(23, 59, 0) {
+    Some(time) => time,
+    None => unreachable!(),
+};
+
+/// Accepts `YYYY-MM-DD` as well as the shorthands `today`, `tomorrow`, `+Nd`, `+Nw` and
+/// `+Nm` (N days, weeks or months from today). Returns `None` for anything else.
+pub fn parse_date_input(input: &str) -> Option<NaiveDate> {
+    parse_date_relative_to(input, Local::now().date_naive())
+}
+
+/// `parse_date_input` with the shorthands counted from `today`
+fn parse_date_relative_to(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let input = input.to_ascii_lowercase();
+    match input.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return today.checked_add_days(Days::new(1)),
+        _ => {}
+    }
+
+    let offset = input.strip_prefix('+')?;
+    let unit = offset.chars().last()?;
+    let count: u32 = offset[..offset.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'd' => today.checked_add_days(Days::new(count.into())),
+        'w' => today.checked_add_days(Days::new(u64::from(count) * 7)),
+        // Clamps to the end of shorter months, e.g. Jan 31 + 1m is Feb 28/29
+        'm' => today.checked_add_months(Months::new(count)),
+        _ => None,
+    }
+}
+
+/// Anything `parse_date_input` accepts, optionally followed by a `HH:MM` time
+/// (`2025-08-01 14:30`, `tomorrow 9:00`). Without a time the deadline is `END_OF_DAY`.
+pub fn parse_datetime_input(input: &str) -> Option<NaiveDateTime> {
+    let input = input.trim();
+    if let Some((date, time)) = input.rsplit_once(char::is_whitespace)
+        && let Ok(time) = NaiveTime::parse_from_str(time, "%H:%M")
+    {
+        return Some(parse_date_input(date)?.and_time(time));
+    }
+    Some(parse_date_input(input)?.and_time(END_OF_DAY))
+}
+
+/// `2025-08-01` for end-of-day deadlines, `2025-08-01 14:30` otherwise
+pub fn format_deadline(deadline: NaiveDateTime) -> String {
+    if deadline.time() == END_OF_DAY {
+        deadline.format("%Y-%m-%d").to_string()
+    } else {
+        deadline.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn parse(input: &str) -> Option<NaiveDate> {
+        parse_date_relative_to(input, date(2025, 1, 31))
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(parse("2025-12-31"), Some(date(2025, 12, 31)));
+        assert_eq!(parse("  2024-02-29 "), Some(date(2024, 2, 29)));
+    }
+
+    #[test]
+    fn today_and_tomorrow() {
+        assert_eq!(parse("today"), Some(date(2025, 1, 31)));
+        assert_eq!(parse("Tomorrow"), Some(date(2025, 2, 1)));
+    }
+
+    #[test]
+    fn offsets_in_days_weeks_and_months() {
+        assert_eq!(parse("+0d"), Some(date(2025, 1, 31)));
+        assert_eq!(parse("+3d"), Some(date(2025, 2, 3)));
+        assert_eq!(parse("+2w"), Some(date(2025, 2, 14)));
+        assert_eq!(parse("+2M"), Some(date(2025, 3, 31)));
+        assert_eq!(parse("+12m"), Some(date(2026, 1, 31)));
+    }
+
+    #[test]
+    fn month_offsets_clamp_to_the_end_of_shorter_months() {
+        assert_eq!(parse("+1m"), Some(date(2025, 2, 28)));
+        assert_eq!(
+            parse_date_relative_to("+1m", date(2024, 1, 31)),
+            Some(date(2024, 2, 29))
+        );
+        assert_eq!(parse("+3m"), Some(date(2025, 4, 30)));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let inputs = [
+            "", "yesterday", "2025-02-30", "31/01/2025", "+", "+d", "3d", "-3d", "+3y", "+1.5w",
+            "+99999999999d",
+        ];
+        for input in inputs {
+            assert_eq!(parse(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn datetimes_default_to_the_end_of_the_day() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(
+            parse_datetime_input("2025-08-01 14:30"),
+            Some(date(2025, 8, 1).and_time(time(14, 30)))
+        );
+        assert_eq!(parse_datetime_input("2025-08-01"), Some(date(2025, 8, 1).and_time(END_OF_DAY)));
+        assert_eq!(parse_datetime_input("2025-08-01 25:00"), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! Writers for sharing the task list outside rtodo.
+
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::date_utils;
+use crate::import;
+use crate::{Priority, TodoItem};
+
+const CSV_HEADER: [&str; 8] = [
+    "id",
+    "title",
+    "description",
+    "target_date",
+    "created_date",
+    "completed",
+    "priority",
+    "tags",
+];
+
+/// Renders `items` as RFC 4180 CSV with a header row. Free-text columns are always quoted.
+pub fn to_csv<'a>(items: impl IntoIterator<Item = &'a TodoItem>) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push_str("\r\n");
+    for item in items {
+        let row = [
+            item.id.to_string(),
+            quote(&item.title),
+            quote(&item.description),
+            date_utils::format_deadline(item.target_datetime),
+            item.created_date.format("%Y-%m-%d").to_string(),
+            item.completed.to_string(),
+            item.priority.label().to_string(),
+            quote(&item.tags.join(",")),
+        ];
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Renders `items` as a Markdown checklist under `## Overdue`, `## Upcoming` and
+/// `## Completed`, keeping the order they are given in. A description follows its task on
+/// the same line, with its lines joined.
+pub fn to_markdown<'a>(items: impl IntoIterator<Item = &'a TodoItem>) -> String {
+    let items: Vec<&TodoItem> = items.into_iter().collect();
+    let section = |item: &TodoItem| {
+        if item.completed {
+            "Completed"
+        } else if item.is_overdue() {
+            "Overdue"
+        } else {
+            "Upcoming"
+        }
+    };
+
+    let mut out = String::new();
+    for heading in ["Overdue", "Upcoming", "Completed"] {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n", heading));
+        for item in items.iter().filter(|item| section(item) == heading) {
+            out.push_str(&format!(
+                "- [{}] {} (due {})",
+                if item.completed { "x" } else { " " },
+                item.title,
+                date_utils::format_deadline(item.target_datetime)
+            ));
+            let description: Vec<&str> = item
+                .description
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+            if !description.is_empty() {
+                out.push_str(&format!(" — {}", description.join(" ")));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Renders `items` in the todo.txt format, one task per line: `x` and the completion date
+/// for done tasks, a priority letter (Medium, the default, gets none), the creation date,
+/// the title, tags as `+tag` and the target date as `due:YYYY-MM-DD`. todo.txt has no
+/// room for descriptions, notes or times of day, so those are left out.
+///
+/// Title words that would read back as something else, like `+word`, `key:value` or a
+/// leading date, are escaped with a `\` that `import::from_todo_txt` removes again.
+pub fn to_todo_txt<'a>(items: impl IntoIterator<Item = &'a TodoItem>) -> String {
+    let mut out = String::new();
+    for item in items {
+        let mut parts = Vec::new();
+        let priority = match item.priority {
+            Priority::Critical => Some('A'),
+            Priority::High => Some('B'),
+            Priority::Medium => None,
+            Priority::Low => Some('D'),
+        };
+        if item.completed {
+            parts.push("x".to_string());
+            // A single date after `x` reads as the completion date, so the creation date
+            // can only be written alongside one
+            if let Some(completed_date) = item.completed_date {
+                parts.push(completed_date.format("%Y-%m-%d").to_string());
+                parts.push(item.created_date.format("%Y-%m-%d").to_string());
+            }
+        } else {
+            if let Some(priority) = priority {
+                parts.push(format!("({})", priority));
+            }
+            parts.push(item.created_date.format("%Y-%m-%d").to_string());
+        }
+        parts.push(todo_txt_title(&item.title));
+        parts.extend(item.tags.iter().map(|tag| format!("+{}", tag)));
+        parts.push(format!("due:{}", item.target_datetime.format("%Y-%m-%d")));
+        // Done tasks lose their `(A)` marker, as todo.txt clients do, so keep it as a tag
+        if item.completed
+            && let Some(priority) = priority
+        {
+            parts.push(format!("pri:{}", priority));
+        }
+        out.push_str(&parts.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+fn todo_txt_title(title: &str) -> String {
+    let words = title.split_whitespace().enumerate().map(|(index, word)| {
+        // Only the first word can be mistaken for the completion mark, a priority or a date
+        let leading = index == 0
+            && (word == "x"
+                || import::todo_txt_priority(word).is_some()
+                || NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok());
+        if leading
+            || word.starts_with('\\')
+            || import::todo_txt_tag(word).is_some()
+            || import::todo_txt_key_value(word).is_some()
+        {
+            format!("\\{}", word)
+        } else {
+            word.to_string()
+        }
+    });
+    words.collect::<Vec<_>>().join(" ")
+}
+
+/// Renders `items` as an RFC 5545 iCalendar file of VTODO entries. Each UID combines the
+/// task id with a hash of `source`, the list's path, so re-importing an updated export
+/// replaces the earlier copy of a task instead of adding another.
+pub fn to_ical<'a>(items: impl IntoIterator<Item = &'a TodoItem>, source: &Path) -> String {
+    let source_hash = fnv1a(source.to_string_lossy().as_bytes());
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rtodo//rtodo//EN".to_string(),
+    ];
+    for item in items {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}-{:016x}@rtodo", item.id, source_hash));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("CREATED:{}", ical_utc(item.created_date.and_time(Default::default()))));
+        lines.push(format!("SUMMARY:{}", ical_escape(&item.title)));
+        if !item.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", ical_escape(&item.description)));
+        }
+        // Floating local time, the way the deadline was entered
+        lines.push(format!("DUE:{}", item.target_datetime.format("%Y%m%dT%H%M%S")));
+        // 1 is the highest priority and 9 the lowest
+        let priority = match item.priority {
+            Priority::Critical => 1,
+            Priority::High => 3,
+            Priority::Medium => 5,
+            Priority::Low => 9,
+        };
+        lines.push(format!("PRIORITY:{}", priority));
+        if !item.tags.is_empty() {
+            let tags: Vec<String> = item.tags.iter().map(|tag| ical_escape(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if item.completed {
+            lines.push("STATUS:COMPLETED".to_string());
+            if let Some(completed_date) = item.completed_date {
+                lines.push(format!("COMPLETED:{}", ical_utc(completed_date.and_time(Default::default()))));
+            }
+        } else {
+            lines.push("STATUS:NEEDS-ACTION".to_string());
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&ical_fold(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Renders `items` as an Org-mode outline: a `TODO` or `DONE` heading per task with its
+/// tags, `CLOSED:` and `DEADLINE:` planning, a property drawer with the rtodo id and
+/// creation date, and the description as body text, indented so it can't start a heading.
+pub fn to_org<'a>(items: impl IntoIterator<Item = &'a TodoItem>) -> String {
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&format!(
+            "* {} {}",
+            if item.completed { "DONE" } else { "TODO" },
+            item.title.replace('\n', " ")
+        ));
+        if !item.tags.is_empty() {
+            // Org tags are limited to letters, digits, `_`, `@`, `#` and `%`
+            let tags: Vec<String> = item
+                .tags
+                .iter()
+                .map(|tag| {
+                    tag.chars()
+                        .map(|c| if c.is_alphanumeric() || "_@#%".contains(c) { c } else { '_' })
+                        .collect()
+                })
+                .collect();
+            out.push_str(&format!(" :{}:", tags.join(":")));
+        }
+        out.push('\n');
+
+        let mut planning = Vec::new();
+        if let Some(completed_date) = item.completed_date {
+            planning.push(format!("CLOSED: [{}]", completed_date.format("%Y-%m-%d %a")));
+        }
+        let deadline = if item.target_datetime.time() == date_utils::END_OF_DAY {
+            item.target_datetime.format("%Y-%m-%d %a")
+        } else {
+            item.target_datetime.format("%Y-%m-%d %a %H:%M")
+        };
+        planning.push(format!("DEADLINE: <{}>", deadline));
+        out.push_str(&format!("  {}\n", planning.join(" ")));
+
+        out.push_str("  :PROPERTIES:\n");
+        out.push_str(&format!("  :RTODO_ID: {}\n", item.id));
+        out.push_str(&format!("  :CREATED:  [{}]\n", item.created_date.format("%Y-%m-%d %a")));
+        out.push_str("  :END:\n");
+        for line in item.description.lines() {
+            out.push_str(&format!("  {}\n", line.trim_end()));
+        }
+    }
+    out
+}
+
+/// A local date and time as an iCalendar UTC timestamp
+fn ical_utc(local: NaiveDateTime) -> String {
+    let utc = Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map_or(local, |time| time.naive_utc());
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes a TEXT value: backslashes, semicolons, commas and line breaks
+fn ical_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Splits a content line into lines of at most 75 octets, continued with a leading space,
+/// without breaking up a multi-byte character
+fn ical_fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            line_len = 1;
+        }
+        out.push(c);
+        line_len += c.len_utf8();
+    }
+    out
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s hasher is the same on every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_utils::END_OF_DAY;
+
+    fn task(title: &str) -> TodoItem {
+        let due = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_time(END_OF_DAY);
+        let mut item = TodoItem::new(1, title.to_string(), String::new(), due);
+        item.created_date = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        item
+    }
+
+    #[test]
+    fn todo_txt_line_layout() {
+        let mut item = task("Call mom");
+        item.priority = Priority::Critical;
+        item.tags = vec!["family".to_string()];
+        assert_eq!(to_todo_txt([&item]), "(A) 2025-02-01 Call mom +family due:2025-03-01\n");
+
+        item.completed = true;
+        item.completed_date = NaiveDate::from_ymd_opt(2025, 2, 3);
+        assert_eq!(
+            to_todo_txt([&item]),
+            "x 2025-02-03 2025-02-01 Call mom +family due:2025-03-01 pri:A\n"
+        );
+
+        item.completed_date = None;
+        assert_eq!(to_todo_txt([&item]), "x Call mom +family due:2025-03-01 pri:A\n");
+    }
+
+    #[test]
+    fn todo_txt_titles_survive_a_round_trip() {
+        let titles = [
+            "Plain title",
+            "Vote +1 on the RFC",
+            "Email @bob",
+            "Meet at 10:30",
+            "due:tomorrow is not a date",
+            "x marks the spot",
+            "2025-01-01 retrospective",
+            "(A) is not a priority",
+            "Back\\slash \\+ and \\o/",
+            "Read https://example.com/post",
+        ];
+        for completed in [false, true] {
+            for title in titles {
+                let mut item = task(title);
+                item.completed = completed;
+                let line = to_todo_txt([&item]);
+
+                let result = import::from_todo_txt(&line);
+
+                assert!(result.errors.is_empty(), "{:?}: {:?}", line, result.errors);
+                let imported = &result.items[0];
+                assert_eq!(imported.title, title, "{:?}", line);
+                assert_eq!(imported.completed, completed, "{:?}", line);
+                assert!(imported.tags.is_empty(), "{:?}", line);
+                assert!(imported.description.is_empty(), "{:?}", line);
+            }
+        }
+    }
+}
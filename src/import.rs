@@ -0,0 +1,743 @@
+//! Readers that turn files from other tools back into tasks.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::Deserialize;
+
+use crate::date_utils::END_OF_DAY;
+use crate::{Priority, TodoItem};
+
+/// Imported tasks without a target date are due this many days from today
+const DEFAULT_DUE_DAYS: u64 = 7;
+
+/// Tasks parsed from an import file, plus a human-readable reason for every row that was skipped
+#[derive(Default)]
+pub struct ImportResult {
+    pub items: Vec<TodoItem>,
+    pub errors: Vec<String>,
+    // Entries left out on purpose, like tasks deleted in the other tool
+    pub ignored: usize,
+}
+
+/// Layouts `read_file` understands
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    Markdown,
+    TodoTxt,
+    Taskwarrior,
+}
+
+impl ImportFormat {
+    /// `.md`/`.markdown` is a Markdown checklist, `.txt` todo.txt, `.json` a Taskwarrior
+    /// export and anything else CSV
+    fn from_path(path: &Path) -> Self {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => ImportFormat::Markdown,
+            "txt" => ImportFormat::TodoTxt,
+            "json" => ImportFormat::Taskwarrior,
+            _ => ImportFormat::Csv,
+        }
+    }
+}
+
+/// Reads the file at `path` in `format`, or else the format its extension suggests
+pub fn read_file(path: &Path, format: Option<ImportFormat>) -> Result<ImportResult, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.kind().to_string())?;
+    match format.unwrap_or_else(|| ImportFormat::from_path(path)) {
+        ImportFormat::Csv => from_csv(&content),
+        ImportFormat::Markdown => Ok(from_markdown(&content)),
+        ImportFormat::TodoTxt => Ok(from_todo_txt(&content)),
+        ImportFormat::Taskwarrior => from_taskwarrior(&content),
+    }
+}
+
+/// Parses CSV in the layout written by `export::to_csv`. Columns are matched by header name;
+/// only `title` is required, and tasks without a `target_date` are due in `DEFAULT_DUE_DAYS`.
+/// Imported tasks get id 0 and must be renumbered by the caller.
+pub fn from_csv(content: &str) -> Result<ImportResult, String> {
+    let mut records = parse_csv(content)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err("file is empty".to_string());
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let Some(title_col) = column("title") else {
+        return Err("missing `title` column".to_string());
+    };
+    let target_col = column("target_date");
+    let description_col = column("description");
+    let created_col = column("created_date");
+    let completed_col = column("completed");
+    let priority_col = column("priority");
+    let tags_col = column("tags");
+
+    let mut result = ImportResult::default();
+    for (line, record) in records {
+        if record.len() == 1 && record[0].is_empty() {
+            continue; // blank line
+        }
+        if record.len() != header.len() {
+            result.errors.push(format!(
+                "line {}: expected {} fields, found {}",
+                line,
+                header.len(),
+                record.len()
+            ));
+            continue;
+        }
+        match csv_record_to_item(
+            &record,
+            title_col,
+            target_col,
+            [description_col, created_col, completed_col, priority_col, tags_col],
+        ) {
+            Ok(item) => result.items.push(item),
+            Err(err) => result.errors.push(format!("line {}: {}", line, err)),
+        }
+    }
+    Ok(result)
+}
+
+fn csv_record_to_item(
+    record: &[String],
+    title_col: usize,
+    target_col: Option<usize>,
+    [description_col, created_col, completed_col, priority_col, tags_col]: [Option<usize>; 5],
+) -> Result<TodoItem, String> {
+    let field = |col: Option<usize>| col.map(|c| record[c].trim()).filter(|v| !v.is_empty());
+
+    let title = record[title_col].trim();
+    if title.is_empty() {
+        return Err("empty title".to_string());
+    }
+    let target_datetime = match field(target_col) {
+        Some(target) => parse_deadline(target)?,
+        None => default_deadline()?,
+    };
+    let description = description_col.map(|c| record[c].clone()).unwrap_or_default();
+
+    let mut item = TodoItem::new(0, title.to_string(), description, target_datetime);
+    if let Some(created) = field(created_col) {
+        item.created_date = parse_date(created)?;
+    }
+    if let Some(completed) = field(completed_col) {
+        item.completed = match completed.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" | "x" => true,
+            "false" | "no" | "0" => false,
+            other => return Err(format!("invalid completed value `{}`", other)),
+        };
+    }
+    if let Some(priority) = field(priority_col) {
+        item.priority = Priority::from_label(priority)
+            .ok_or_else(|| format!("unknown priority `{}`", priority))?;
+    }
+    if let Some(tags) = field(tags_col) {
+        item.tags = tags
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches('#'))
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    Ok(item)
+}
+
+/// A checklist item being read, until a line that isn't nested under it
+struct ChecklistItem {
+    line: usize,
+    indent: usize,
+    completed: bool,
+    text: String,
+    nested: Vec<String>,
+}
+
+/// Turns the `- [ ]` and `- [x]` items of a Markdown checklist into tasks. Text indented
+/// under an item becomes its description, and a trailing `(due: YYYY-MM-DD)` its target
+/// date. Reads back what `export::to_markdown` writes; everything else is ignored.
+pub fn from_markdown(content: &str) -> ImportResult {
+    let mut result = ImportResult::default();
+    let mut current: Option<ChecklistItem> = None;
+    for (index, line) in content.lines().enumerate() {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if let Some(item) = &mut current
+            && indent > item.indent
+        {
+            item.nested.push(strip_bullet(text).to_string());
+            continue;
+        }
+        if let Some(item) = current.take() {
+            push_checklist_item(&mut result, item);
+        }
+        current = checkbox(text).map(|(completed, text)| ChecklistItem {
+            line: index + 1,
+            indent,
+            completed,
+            text: text.to_string(),
+            nested: Vec::new(),
+        });
+    }
+    if let Some(item) = current {
+        push_checklist_item(&mut result, item);
+    }
+    result
+}
+
+fn push_checklist_item(result: &mut ImportResult, item: ChecklistItem) {
+    let line = item.line;
+    match checklist_item_to_task(item) {
+        Ok(task) => result.items.push(task),
+        Err(err) => result.errors.push(format!("line {}: {}", line, err)),
+    }
+}
+
+fn checklist_item_to_task(item: ChecklistItem) -> Result<TodoItem, String> {
+    // The exporter puts the description after a dash: `Title (due 2025-01-15) — description`
+    let (text, inline_description) = match item.text.split_once(" — ") {
+        Some((text, description)) => (text, Some(description)),
+        None => (item.text.as_str(), None),
+    };
+    let (title, target_datetime) = match text
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .and_then(|(title, due)| {
+            let date = due.strip_prefix("due:").or_else(|| due.strip_prefix("due "))?;
+            Some((title, date))
+        }) {
+        Some((title, date)) => (title.trim(), parse_deadline(date)?),
+        None => (text.trim(), default_deadline()?),
+    };
+    if title.is_empty() {
+        return Err("empty title".to_string());
+    }
+    let description: Vec<&str> = inline_description
+        .into_iter()
+        .chain(item.nested.iter().map(String::as_str))
+        .collect();
+
+    let mut task = TodoItem::new(0, title.to_string(), description.join("\n"), target_datetime);
+    task.completed = item.completed;
+    Ok(task)
+}
+
+/// Whether a `- [ ] text` line is checked, and its text; `None` for any other line
+fn checkbox(line: &str) -> Option<(bool, &str)> {
+    let rest = strip_bullet(line);
+    if rest.len() == line.len() {
+        return None;
+    }
+    let (completed, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, text)
+    } else {
+        return None;
+    };
+    Some((completed, text.trim()))
+}
+
+/// `line` without a leading `- `, `* ` or `+ ` list marker
+fn strip_bullet(line: &str) -> &str {
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .unwrap_or(line)
+}
+
+/// Parses the todo.txt format as written by `export::to_todo_txt`: an optional `x` and
+/// completion date, `(A)`-style priority, creation date, then the text. `+project` and
+/// `@context` words become tags, `due:` the target date and `pri:` the priority; any other
+/// `key:value` is kept as a line of the description. A word starting with `\` is title text
+/// with the backslash removed, however it would otherwise read.
+pub fn from_todo_txt(content: &str) -> ImportResult {
+    let mut result = ImportResult::default();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match todo_txt_line_to_item(line) {
+            Ok(item) => result.items.push(item),
+            Err(err) => result.errors.push(format!("line {}: {}", index + 1, err)),
+        }
+    }
+    result
+}
+
+fn todo_txt_line_to_item(line: &str) -> Result<TodoItem, String> {
+    let mut words = line.split_whitespace().peekable();
+    let completed = words.next_if_eq(&"x").is_some();
+    let mut priority = words.next_if(|word| todo_txt_priority(word).is_some()).and_then(todo_txt_priority);
+    let is_date = |word: &&str| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok();
+    let first_date = words.next_if(is_date).map(parse_date).transpose()?;
+    let second_date = words.next_if(is_date).map(parse_date).transpose()?;
+    // After `x` one date is the completion date and two are completion and creation
+    let (completed_date, created_date) = if completed {
+        (first_date, second_date.or(first_date))
+    } else {
+        (None, first_date)
+    };
+
+    let mut title = Vec::new();
+    let mut tags = Vec::new();
+    let mut extra = Vec::new();
+    let mut target_datetime = None;
+    for word in words {
+        if let Some(escaped) = word.strip_prefix('\\') {
+            title.push(escaped);
+            continue;
+        }
+        if let Some(tag) = todo_txt_tag(word) {
+            tags.push(tag.to_string());
+            continue;
+        }
+        let Some((key, value)) = todo_txt_key_value(word) else {
+            title.push(word);
+            continue;
+        };
+        match key {
+            "due" => target_datetime = Some(parse_date(value)?.and_time(END_OF_DAY)),
+            "pri" => {
+                priority = Some(
+                    todo_txt_priority(&format!("({})", value))
+                        .ok_or_else(|| format!("invalid priority `{}`", value))?,
+                )
+            }
+            _ => extra.push(word),
+        }
+    }
+    if title.is_empty() {
+        return Err("empty title".to_string());
+    }
+
+    let target_datetime = match target_datetime {
+        Some(target) => target,
+        None => default_deadline()?,
+    };
+    let mut item = TodoItem::new(0, title.join(" "), extra.join("\n"), target_datetime);
+    item.completed = completed;
+    item.completed_date = completed_date;
+    if let Some(created_date) = created_date {
+        item.created_date = created_date;
+    }
+    if let Some(priority) = priority {
+        item.priority = priority;
+    }
+    item.tags = tags;
+    Ok(item)
+}
+
+/// One task of `task export`; other fields are ignored
+#[derive(Deserialize)]
+struct TaskwarriorTask {
+    description: Option<String>,
+    status: Option<String>,
+    entry: Option<String>,
+    due: Option<String>,
+    end: Option<String>,
+    priority: Option<String>,
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    annotations: Vec<TaskwarriorAnnotation>,
+}
+
+#[derive(Deserialize)]
+struct TaskwarriorAnnotation {
+    description: String,
+}
+
+/// Parses the JSON array written by Taskwarrior's `task export`. Deleted and waiting tasks,
+/// and the templates recurring tasks are generated from, are left out and counted as
+/// ignored. Timestamps are UTC and converted to local time; a task due at local midnight
+/// (what `due:2025-01-10` sets) counts as due at the end of that day.
+pub fn from_taskwarrior(content: &str) -> Result<ImportResult, String> {
+    let tasks: Vec<serde_json::Value> =
+        serde_json::from_str(content).map_err(|err| format!("not a Taskwarrior export: {}", err))?;
+    let mut result = ImportResult::default();
+    for (index, task) in tasks.into_iter().enumerate() {
+        let task = match TaskwarriorTask::deserialize(task) {
+            Ok(task) => task,
+            Err(err) => {
+                result.errors.push(format!("task {}: {}", index + 1, err));
+                continue;
+            }
+        };
+        if matches!(task.status.as_deref(), Some("deleted" | "waiting" | "recurring")) {
+            result.ignored += 1;
+            continue;
+        }
+        match taskwarrior_task_to_item(task) {
+            Ok(item) => result.items.push(item),
+            Err(err) => result.errors.push(format!("task {}: {}", index + 1, err)),
+        }
+    }
+    Ok(result)
+}
+
+fn taskwarrior_task_to_item(task: TaskwarriorTask) -> Result<TodoItem, String> {
+    let title = task.description.as_deref().map(str::trim).unwrap_or_default();
+    if title.is_empty() {
+        return Err("empty description".to_string());
+    }
+    let target_datetime = match task.due.as_deref() {
+        Some(due) => {
+            let due = parse_taskwarrior_timestamp(due)?;
+            if due.time() == NaiveTime::MIN {
+                due.date().and_time(END_OF_DAY)
+            } else {
+                due
+            }
+        }
+        None => default_deadline()?,
+    };
+    let description: Vec<&str> =
+        task.annotations.iter().map(|annotation| annotation.description.trim()).collect();
+
+    let mut item = TodoItem::new(0, title.to_string(), description.join("\n"), target_datetime);
+    if let Some(entry) = task.entry.as_deref() {
+        item.created_date = parse_taskwarrior_timestamp(entry)?.date();
+    }
+    item.completed = task.status.as_deref() == Some("completed");
+    if item.completed
+        && let Some(end) = task.end.as_deref()
+    {
+        item.completed_date = Some(parse_taskwarrior_timestamp(end)?.date());
+    }
+    item.priority = match task.priority.as_deref() {
+        Some("H") => Priority::High,
+        Some("L") => Priority::Low,
+        _ => Priority::Medium,
+    };
+    // rtodo's projects are separate files, so a Taskwarrior project is kept as a tag
+    item.tags = task.project.into_iter().chain(task.tags).collect();
+    Ok(item)
+}
+
+/// A Taskwarrior UTC timestamp, `20250110T000000Z` or RFC 3339, in local time
+fn parse_taskwarrior_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    let utc = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .map(|time| time.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)))
+        .map_err(|_| format!("invalid timestamp `{}`", value))?;
+    Ok(utc.with_timezone(&Local).naive_local())
+}
+
+/// `project` for `+project`, `context` for `@context`
+pub fn todo_txt_tag(word: &str) -> Option<&str> {
+    word.strip_prefix('+')
+        .or_else(|| word.strip_prefix('@'))
+        .filter(|tag| !tag.is_empty())
+}
+
+/// `("due", "2025-01-15")` for `due:2025-01-15`. Links like `https://...` are text.
+pub fn todo_txt_key_value(word: &str) -> Option<(&str, &str)> {
+    word.split_once(':')
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty() && !value.starts_with('/'))
+}
+
+/// `(A)` is Critical, `(B)` High, `(C)` Medium and anything lower Low
+pub fn todo_txt_priority(word: &str) -> Option<Priority> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    match letter {
+        "A" => Some(Priority::Critical),
+        "B" => Some(Priority::High),
+        "C" => Some(Priority::Medium),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Where tasks imported without a target date are due: `DEFAULT_DUE_DAYS` from today
+fn default_deadline() -> Result<NaiveDateTime, String> {
+    Ok(Local::now()
+        .date_naive()
+        .checked_add_days(Days::new(DEFAULT_DUE_DAYS))
+        .ok_or("date out of range")?
+        .and_time(END_OF_DAY))
+}
+
+/// `YYYY-MM-DD HH:MM`, or a bare date due at the end of the day
+fn parse_deadline(value: &str) -> Result<NaiveDateTime, String> {
+    if let Some((date, time)) = value.trim().split_once(' ')
+        && let Ok(time) = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+    {
+        return Ok(parse_date(date)?.and_time(time));
+    }
+    Ok(parse_date(value)?.and_time(END_OF_DAY))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("invalid date `{}`, expected YYYY-MM-DD", value.trim()))
+}
+
+/// Splits RFC 4180 CSV into records, each tagged with the line number it starts on.
+/// Quoted fields may contain commas, doubled quotes and line breaks.
+fn parse_csv(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("unterminated quoted field starting on line {}", record_line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// The single task on a todo.txt line
+    fn todo_txt(line: &str) -> TodoItem {
+        let mut result = from_todo_txt(line);
+        assert!(result.errors.is_empty(), "{:?}: {:?}", line, result.errors);
+        assert_eq!(result.items.len(), 1, "{:?}", line);
+        result.items.remove(0)
+    }
+
+    #[test]
+    fn todo_txt_pending_task_with_everything() {
+        let item = todo_txt("(A) 2025-01-10 Call mom +family @phone due:2025-01-15");
+
+        assert_eq!(item.title, "Call mom");
+        assert!(!item.completed);
+        assert!(item.priority == Priority::Critical);
+        assert_eq!(item.created_date, date(2025, 1, 10));
+        assert_eq!(item.tags, ["family", "phone"]);
+        assert_eq!(item.target_datetime, date(2025, 1, 15).and_time(END_OF_DAY));
+    }
+
+    #[test]
+    fn todo_txt_completed_task_with_both_dates() {
+        let item = todo_txt("x 2025-01-12 2025-01-10 Pay rent due:2025-01-11 pri:B");
+
+        assert!(item.completed);
+        assert_eq!(item.completed_date, Some(date(2025, 1, 12)));
+        assert_eq!(item.created_date, date(2025, 1, 10));
+        assert!(item.priority == Priority::High);
+    }
+
+    #[test]
+    fn todo_txt_completed_task_with_one_date() {
+        let item = todo_txt("x 2025-01-12 Pay rent");
+
+        assert_eq!(item.completed_date, Some(date(2025, 1, 12)));
+        assert_eq!(item.created_date, date(2025, 1, 12));
+    }
+
+    #[test]
+    fn todo_txt_completed_task_without_dates() {
+        let item = todo_txt("x Pay rent");
+
+        assert_eq!(item.title, "Pay rent");
+        assert!(item.completed);
+        assert_eq!(item.completed_date, None);
+        assert_eq!(item.created_date, Local::now().date_naive());
+        assert_eq!(item.target_datetime, default_deadline().unwrap());
+    }
+
+    #[test]
+    fn todo_txt_bare_title() {
+        let item = todo_txt("Buy milk");
+
+        assert_eq!(item.title, "Buy milk");
+        assert!(!item.completed);
+        assert!(item.priority == Priority::Medium);
+        assert!(item.tags.is_empty());
+    }
+
+    #[test]
+    fn todo_txt_words_that_only_look_special() {
+        // Only a lowercase `x` marks completion, and only uppercase letters are priorities
+        assert_eq!(todo_txt("X marks the spot").title, "X marks the spot");
+        assert!(!todo_txt("X marks the spot").completed);
+        assert_eq!(todo_txt("(a) lower case").title, "(a) lower case");
+        // A lone `+` or `@` isn't a tag, and a link isn't a key:value pair
+        assert_eq!(todo_txt("1 + 1 @ home").title, "1 + 1 @ home");
+        assert_eq!(
+            todo_txt("Read https://example.com/post").title,
+            "Read https://example.com/post"
+        );
+    }
+
+    #[test]
+    fn todo_txt_unknown_key_values_go_to_the_description() {
+        let item = todo_txt("Plan trip location:paris budget:500");
+
+        assert_eq!(item.title, "Plan trip");
+        assert_eq!(item.description, "location:paris\nbudget:500");
+    }
+
+    #[test]
+    fn todo_txt_escaped_words_stay_in_the_title() {
+        let item = todo_txt("\\x 2025-01-10 \\+1 for \\@work at \\10:30 \\\\o/");
+
+        assert_eq!(item.title, "x 2025-01-10 +1 for @work at 10:30 \\o/");
+        assert!(!item.completed);
+        assert!(item.tags.is_empty());
+        assert!(item.description.is_empty());
+    }
+
+    #[test]
+    fn todo_txt_bad_lines_are_reported_with_their_numbers() {
+        let result = from_todo_txt("Good task\n\n+tag due:2025-01-15\nBad date due:2025-13-45\n");
+
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.errors[0].starts_with("line 3:"), "{}", result.errors[0]);
+        assert!(result.errors[1].starts_with("line 4:"), "{}", result.errors[1]);
+    }
+
+    /// The local time `utc` (`YYYY-MM-DD HH:MM:SS`) is in this machine's time zone
+    fn local(utc: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+            .with_timezone(&Local)
+            .naive_local()
+    }
+
+    /// The Taskwarrior timestamp for `time` in this machine's time zone
+    fn taskwarrior_timestamp(time: NaiveDateTime) -> String {
+        time.and_local_timezone(Local)
+            .unwrap()
+            .with_timezone(&Utc)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    }
+
+    /// The single task of a Taskwarrior export
+    fn taskwarrior(json: &str) -> TodoItem {
+        let mut result = from_taskwarrior(json).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.items.len(), 1);
+        result.items.remove(0)
+    }
+
+    #[test]
+    fn taskwarrior_compact_timestamp_is_utc() {
+        assert_eq!(
+            parse_taskwarrior_timestamp("20250110T120000Z"),
+            Ok(local("2025-01-10 12:00:00"))
+        );
+    }
+
+    #[test]
+    fn taskwarrior_rfc3339_timestamp_keeps_its_offset() {
+        assert_eq!(
+            parse_taskwarrior_timestamp("2025-01-10T12:00:00Z"),
+            Ok(local("2025-01-10 12:00:00"))
+        );
+        assert_eq!(
+            parse_taskwarrior_timestamp("2025-01-10T13:30:00+01:30"),
+            Ok(local("2025-01-10 12:00:00"))
+        );
+    }
+
+    #[test]
+    fn taskwarrior_invalid_timestamp() {
+        for value in ["", "2025-01-10", "20250110T120000", "20251310T120000Z", "tomorrow"] {
+            assert!(parse_taskwarrior_timestamp(value).is_err(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn taskwarrior_midnight_due_is_end_of_day() {
+        let due = taskwarrior_timestamp(date(2025, 1, 10).and_time(NaiveTime::MIN));
+        let item = taskwarrior(&format!(r#"[{{"description": "Pay rent", "due": "{}"}}]"#, due));
+
+        assert_eq!(item.target_datetime, date(2025, 1, 10).and_time(END_OF_DAY));
+    }
+
+    #[test]
+    fn taskwarrior_due_time_is_kept() {
+        let time = date(2025, 1, 10).and_hms_opt(9, 30, 0).unwrap();
+        let due = taskwarrior_timestamp(time);
+        let item = taskwarrior(&format!(r#"[{{"description": "Standup", "due": "{}"}}]"#, due));
+
+        assert_eq!(item.target_datetime, time);
+    }
+
+    #[test]
+    fn taskwarrior_completed_task() {
+        let entry = taskwarrior_timestamp(date(2025, 1, 8).and_hms_opt(10, 0, 0).unwrap());
+        let end = taskwarrior_timestamp(date(2025, 1, 9).and_hms_opt(18, 0, 0).unwrap());
+        let item = taskwarrior(&format!(
+            r#"[{{"description": "Pay rent", "status": "completed", "entry": "{}", "end": "{}",
+                 "priority": "H", "project": "home", "tags": ["bills"],
+                 "annotations": [{{"description": "by card"}}]}}]"#,
+            entry, end
+        ));
+
+        assert!(item.completed);
+        assert_eq!(item.created_date, date(2025, 1, 8));
+        assert_eq!(item.completed_date, Some(date(2025, 1, 9)));
+        assert!(item.priority == Priority::High);
+        assert_eq!(item.tags, ["home", "bills"]);
+        assert_eq!(item.description, "by card");
+    }
+
+    #[test]
+    fn taskwarrior_skips_deleted_waiting_and_recurring() {
+        let result = from_taskwarrior(
+            r#"[{"description": "a", "status": "deleted"},
+                {"description": "b", "status": "waiting"},
+                {"description": "c", "status": "recurring"},
+                {"description": "d", "status": "pending"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(result.ignored, 3);
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].title, "d");
+    }
+}
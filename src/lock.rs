@@ -0,0 +1,133 @@
+//! Advisory lock that keeps two rtodo instances from saving over each other.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Held for as long as this instance may write the todo file; removed again on drop,
+/// which also covers a panic unwinding out of `main`
+pub struct FileLock {
+    path: PathBuf,
+}
+
+pub enum LockAttempt {
+    Acquired(FileLock),
+    /// Another instance wrote the lock file. `running` is false when that process is
+    /// known to be gone, i.e. the lock was left behind by a crash.
+    Held {
+        pid: Option<u32>,
+        running: bool,
+    },
+}
+
+/// `todos.json` -> `todos.json.lock`
+pub fn lock_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    save_path.with_file_name(name)
+}
+
+pub fn acquire(save_path: &Path) -> io::Result<LockAttempt> {
+    let path = lock_path(save_path);
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(mut file) => {
+            write!(file, "{}", process::id())?;
+            Ok(LockAttempt::Acquired(FileLock { path }))
+        }
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            let pid = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| content.trim().parse().ok());
+            let running = pid.is_none_or(is_running);
+            Ok(LockAttempt::Held { pid, running })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Takes over a lock whose owner is gone (or which the user chose to override)
+pub fn reclaim(save_path: &Path) -> io::Result<FileLock> {
+    let path = lock_path(save_path);
+    fs::write(&path, process::id().to_string())?;
+    Ok(FileLock { path })
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // If another instance took the lock over in the meantime, it's theirs to remove
+        let ours = fs::read_to_string(&self.path)
+            .is_ok_and(|content| content.trim() == process::id().to_string());
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // `kill -0` only checks that the process exists and may be signalled
+    process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    // No cheap check here, so never treat a lock as stale without asking
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn lock_is_removed_on_drop() {
+        let dir = TempDir::new("lock-drop");
+        let save_path = dir.join("todos.json");
+        let Ok(LockAttempt::Acquired(file_lock)) = acquire(&save_path) else {
+            panic!("lock was not acquired");
+        };
+        assert!(lock_path(&save_path).exists());
+
+        drop(file_lock);
+
+        assert!(!lock_path(&save_path).exists());
+    }
+
+    #[test]
+    fn lock_taken_over_by_another_instance_survives_drop() {
+        let dir = TempDir::new("lock-taken-over");
+        let save_path = dir.join("todos.json");
+        let Ok(LockAttempt::Acquired(file_lock)) = acquire(&save_path) else {
+            panic!("lock was not acquired");
+        };
+        // Another instance decided our lock was stale and reclaimed it
+        let other_pid = process::id() + 1;
+        fs::write(lock_path(&save_path), other_pid.to_string()).unwrap();
+
+        drop(file_lock);
+
+        assert_eq!(fs::read_to_string(lock_path(&save_path)).unwrap(), other_pid.to_string());
+    }
+
+    #[test]
+    fn second_acquire_sees_the_lock_held() {
+        let dir = TempDir::new("lock-held");
+        let save_path = dir.join("todos.json");
+        let _file_lock = acquire(&save_path).unwrap();
+
+        match acquire(&save_path).unwrap() {
+            LockAttempt::Held { pid, running } => {
+                assert_eq!(pid, Some(process::id()));
+                assert!(running);
+            }
+            LockAttempt::Acquired(_) => panic!("lock acquired twice"),
+        }
+    }
+}
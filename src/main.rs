@@ -1,34 +1,473 @@
-use color_eyre::Result;
-use crossterm::event::KeyModifiers;
+mod changelog;
+mod clipboard;
+mod config;
+mod crypto;
+mod date_utils;
+mod export;
+mod import;
+mod lock;
+mod merge;
+mod storage;
+#[cfg(test)]
+mod test_support;
+mod theme;
+#[cfg(feature = "watch")]
+mod watch;
+
+use clap::{Parser, Subcommand};
+use color_eyre::{eyre::{eyre, WrapErr}, Result};
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
-    layout::{Constraint, Layout, Margin, Rect, Alignment},
-    style::{Color, Modifier, Style, Stylize},
+    crossterm::event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    crossterm::{execute, terminal},
+    layout::{Constraint, Layout, Margin, Position, Rect, Alignment},
+    style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{
-        Block, BorderType, Cell, Gauge, HighlightSpacing, Paragraph, Row, Scrollbar,
-        ScrollbarOrientation, ScrollbarState, Table, TableState, Clear,
+        Bar, BarChart, BarGroup, Block, Cell, Gauge, HighlightSpacing, Paragraph, Row, ScrollbarState, Table, TableState,
+        Clear, Wrap,
     },
     DefaultTerminal, Frame,
 };
-use chrono::{Local, NaiveDate, Datelike};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, SubsecRound};
 use serde::{Deserialize, Serialize};
+use directories::ProjectDirs;
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use config::Config;
+use theme::Theme;
+use storage::{AppState, ParseError, Settings, StorageFormat};
 
 const SAVE_FILE: &str = "todos.json";
 
+/// Table column headers, in display order
+const COLUMN_TITLES: [&str; 7] = [
+    "Title",
+    "Description",
+    "Tags",
+    "Target Date",
+    "Created",
+    "Priority",
+    "Status",
+];
+
+/// Index of the Status column, which toggles completion when clicked
+const STATUS_COLUMN: usize = COLUMN_TITLES.len() - 1;
+
+/// Width of the gutter left of the title, where tasks marked for a bulk action show a check box
+const MARK_COLUMN_WIDTH: u16 = 1;
+
+/// Title length past which the form's character count turns red
+const TITLE_LENGTH_WARNING: usize = 60;
+
+/// Tasks overdue by more than this many days are shown in bold, in the severe overdue color
+const SEVERELY_OVERDUE_DAYS: i64 = 7;
+
+/// Narrowest a column can be resized to, and the step Shift+←/→ resize by, in percent
+const MIN_COLUMN_WIDTH: u16 = 10;
+const COLUMN_RESIZE_STEP: u16 = 5;
+
+/// Terminal width above which the selected task's details are shown beside the table
+const DETAIL_PANE_MIN_WIDTH: u16 = 100;
+
+/// How long the event loop waits for a key before checking on autosave (and file changes)
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Changes are written at most this often, so bursts of edits share one save
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many mutations Ctrl+Z can step back through
+const UNDO_LIMIT: usize = 50;
+
+/// Two clicks on the same row within this interval open the edit form
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Tasks stay in the trash for this many days before being purged on startup
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Entries of the right-click menu, in display order
+const CONTEXT_MENU_ITEMS: [&str; 3] = ["Delete", "Toggle Complete", "Edit"];
+
+/// A heading and its (key, action) pairs
+type HelpSection = (&'static str, &'static [(&'static str, &'static str)]);
+
+/// Every key binding, by category, as shown in the `?` overlay
+const HELP_SECTIONS: &[HelpSection] = &[
+    (
+        "Navigation",
+        &[
+            ("↑ / ↓", "Move between tasks"),
+            ("Home / End", "Jump to the first / last task"),
+            ("{ / }", "Previous / next task due today or overdue"),
+            ("Mouse", "Click selects, scroll moves"),
+            ("Esc / q", "Clear selection or filter, then quit"),
+        ],
+    ),
+    (
+        "Tasks",
+        &[
+            ("N", "Add a task"),
+            ("E / double-click", "Edit the selected task"),
+            ("D", "Move to the trash (asks first)"),
+            ("Space / click Status", "Toggle complete"),
+            ("p", "Cycle priority"),
+            ("Enter", "Show / hide the task's subtasks"),
+            ("+ / 1-9 / -", "Add / tick / clear done subtasks"),
+            ("Shift+O", "Edit notes full-screen (Ctrl+S saves)"),
+            ("V", "Select for bulk Space / D"),
+            ("Right-click", "Delete / toggle / edit menu"),
+            ("h", "History of the task (with --change-log)"),
+            ("Ctrl+C", "Copy the task's title"),
+            ("Ctrl+Z / Ctrl+Y", "Undo / redo"),
+        ],
+    ),
+    (
+        "Sorting and filtering",
+        &[
+            ("s / T / C / o", "Sort by date / target / completion / priority"),
+            ("← / →", "Pick a column to resize"),
+            ("Shift+← / →", "Narrow / widen that column"),
+            ("Shift+R", "Reverse the sort"),
+            ("Alt/Ctrl+↑ / ↓", "Move a task, sorting by hand"),
+            ("/", "Search (#tag for a tag)"),
+            ("G", "Cycle the tag filter"),
+            ("Shift+H", "Hide / show completed tasks"),
+            ("Shift+W", "Calendar; Enter shows a day's tasks"),
+            ("Shift+S", "Statistics"),
+        ],
+    ),
+    (
+        "Files and projects",
+        &[
+            ("Shift+P", "Project switcher (Shift+N: new)"),
+            ("[ / ]", "Previous / next project"),
+            ("r", "Reload from disk"),
+            ("Ctrl+R", "Restore the newest backup"),
+            ("X / M / L", "Export CSV / Markdown / iCalendar"),
+            ("Shift+I", "Import CSV / Markdown / todo.txt / Taskwarrior"),
+            ("f", "Archive the selected completed task"),
+            ("Shift+A", "Move completed tasks to the archive file"),
+            ("a", "Archive view (U restores a task)"),
+            ("b", "Trash (Enter restores, D purges)"),
+        ],
+    ),
+    (
+        "Task form",
+        &[
+            ("Tab / Shift+Tab", "Next / previous field"),
+            ("← / → / Home / End", "Move the cursor in a text field"),
+            ("← / →", "Change priority or recurrence"),
+            ("Enter", "Submit (new line in Description)"),
+            ("Ctrl+S", "Submit from any field"),
+            ("F2", "Pick the target date on a calendar"),
+            ("Ctrl+V", "Paste from the clipboard"),
+            ("Esc", "Cancel"),
+        ],
+    ),
+    (
+        "Calendar",
+        &[
+            ("Arrows", "Move between days"),
+            ("[ / ]", "Previous / next month"),
+            ("Enter", "Show that day's tasks"),
+            ("Esc", "Back to the list"),
+        ],
+    ),
+];
+
 const INFO_TEXT: [&str; 3] = [
-    "ESC: quit | ↑/↓: navigate | Space: toggle complete | N: new task | E: edit | D: delete",
-"S: sort by date | T: sort by target | C: sort by completion | Enter: confirm edit",
-"Progress tracked automatically - overdue tasks shown in red, completed in green",
+    "?: help | ESC: quit | ↑/↓/Home/End: navigate | Space: toggle complete | N: new task | E: edit | D: delete | b: trash",
+"s/T/C/o: sort by date/target/completion/priority | Shift+R: reverse | ←/→, Shift+←/→: resize columns | p: cycle priority | /: search | G: tag filter | Shift+H: hide done",
+"V: select for bulk Space/D | Ctrl+Z/Y: undo/redo | r: reload | Ctrl+R: restore backup | X/Shift+I: export/import | M/L: export Markdown/iCalendar | f/Shift+A/a: archive task/all done/view archive | Shift+P or [/]: projects | Shift+W: calendar | Shift+S: stats",
 ];
 
-#[derive(Clone, PartialEq)]
+/// A terminal to-do list manager
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Todo file to load and save instead of the default location; takes precedence over
+    /// `RTODO_FILE` and `RTODO_PROFILE`
+    #[arg(short, long, value_name = "PATH")]
+    file: Option<PathBuf>,
+
+    /// Format for a todo file that doesn't exist yet; existing files keep the format their
+    /// extension names
+    #[arg(long, value_enum)]
+    format: Option<StorageFormat>,
+
+    /// Number of rotating backups (`<file>.bak.1` is the newest) kept next to the todo file
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    backups: usize,
+
+    /// Encrypt the todo file with a passphrase (asked for at startup) from now on
+    #[arg(long, conflicts_with = "decrypt")]
+    encrypt: bool,
+
+    /// Store an encrypted todo file as plain text again
+    #[arg(long)]
+    decrypt: bool,
+
+    /// Turn appending every task change to `<name>.log.jsonl` on or off for this list
+    #[arg(long, value_name = "BOOL")]
+    change_log: Option<bool>,
+
+    /// Add the tasks that can still be read from a damaged todo file to the list; defaults to
+    /// the newest `<file>.corrupt-*` copy kept when the todo file failed to load
+    #[arg(long, value_name = "PATH")]
+    recover: Option<Option<PathBuf>>,
+
+    /// Write this list's JSON on a single line instead of indented, which keeps big lists
+    /// small; remembered in the file like `--change-log`
+    #[arg(long, value_name = "BOOL")]
+    compact_json: Option<bool>,
+
+    /// Use a built-in color theme instead of `~/.config/rtodo/theme.toml`
+    #[arg(long, value_enum)]
+    theme: Option<theme::ThemeName>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert a todo file between JSON and TOML, picking formats from the extensions
+    Convert { input: PathBuf, output: PathBuf },
+    /// Write the tasks as CSV (in the list's sort order), Markdown, todo.txt, iCalendar or Org, to stdout unless `-o` is given
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// File to write instead of printing
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Leave out the other tasks
+        #[arg(long, value_enum)]
+        only: Option<TaskStatus>,
+    },
+    /// Add the tasks of a CSV file (as written by `export`), a Markdown checklist, a todo.txt
+    /// file or a Taskwarrior export to the list
+    Import {
+        path: PathBuf,
+        /// Layout of the file; guessed from its extension when left out
+        #[arg(long, value_enum)]
+        format: Option<import::ImportFormat>,
+        /// Add the tasks without asking
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Merge another copy of the todo list into this one, showing what would change first
+    Merge {
+        other: PathBuf,
+        /// Apply the merge without asking
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Markdown,
+    TodoTxt,
+    Ical,
+    Org,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum TaskStatus {
+    Pending,
+    Completed,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 enum SortMode {
+    #[default]
     CreatedDate,
     TargetDate,
     Completion,
+    Priority,
+    // The user's own order, set up by moving tasks with Alt+↑/↓ or Ctrl+↑/↓
+    Manual,
+}
+
+/// Table column widths in percent, adding up to 100. The defaults fit dates and statuses
+/// on an 80-column terminal.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct ColumnWidths {
+    title: u16,
+    description: u16,
+    tags: u16,
+    target: u16,
+    created: u16,
+    priority: u16,
+    status: u16,
+}
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        Self {
+            title: 15,
+            description: 15,
+            tags: 10,
+            target: 16,
+            created: 13,
+            priority: 13,
+            status: 18,
+        }
+    }
+}
+
+impl ColumnWidths {
+    /// Widths in the order of `COLUMN_TITLES`
+    fn to_array(self) -> [u16; 7] {
+        [
+            self.title,
+            self.description,
+            self.tags,
+            self.target,
+            self.created,
+            self.priority,
+            self.status,
+        ]
+    }
+
+    fn from_array([title, description, tags, target, created, priority, status]: [u16; 7]) -> Self {
+        Self { title, description, tags, target, created, priority, status }
+    }
+
+    /// Hand-edited settings that don't add up are replaced by the defaults
+    fn is_valid(self) -> bool {
+        let widths = self.to_array();
+        widths.iter().sum::<u16>() == 100 && widths.iter().all(|&width| width >= MIN_COLUMN_WIDTH)
+    }
+
+    /// Widens (or narrows) `column` by one step, taking the space from (or giving it to) the
+    /// column to its right, or to its left for the last column. None if that would make
+    /// either narrower than `MIN_COLUMN_WIDTH`.
+    fn resize(self, column: usize, grow: bool) -> Option<Self> {
+        let mut widths = self.to_array();
+        let neighbour = if column + 1 < widths.len() { column + 1 } else { column.checked_sub(1)? };
+        let (from, to) = if grow { (neighbour, column) } else { (column, neighbour) };
+        widths[from] = widths[from]
+            .checked_sub(COLUMN_RESIZE_STEP)
+            .filter(|&width| width >= MIN_COLUMN_WIDTH)?;
+        widths[to] += COLUMN_RESIZE_STEP;
+        Some(Self::from_array(widths))
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl Priority {
+    fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Critical => "Critical",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        [Priority::Low, Priority::Medium, Priority::High, Priority::Critical]
+            .into_iter()
+            .find(|priority| priority.label().eq_ignore_ascii_case(label))
+    }
+
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Priority::Low => theme.priority_low_fg,
+            Priority::Medium => theme.priority_medium_fg,
+            Priority::High => theme.priority_high_fg,
+            Priority::Critical => theme.priority_critical_fg,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Critical,
+            Priority::Critical => Priority::Low,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            Priority::Low => Priority::Critical,
+            Priority::Medium => Priority::Low,
+            Priority::High => Priority::Medium,
+            Priority::Critical => Priority::High,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    fn label(recurrence: Option<Self>) -> &'static str {
+        match recurrence {
+            None => "None",
+            Some(Recurrence::Daily) => "Daily",
+            Some(Recurrence::Weekly) => "Weekly",
+            Some(Recurrence::Monthly) => "Monthly",
+            Some(Recurrence::Yearly) => "Yearly",
+        }
+    }
+
+    // Cycling includes "no recurrence", so these work on the Option
+    fn next(recurrence: Option<Self>) -> Option<Self> {
+        match recurrence {
+            None => Some(Recurrence::Daily),
+            Some(Recurrence::Daily) => Some(Recurrence::Weekly),
+            Some(Recurrence::Weekly) => Some(Recurrence::Monthly),
+            Some(Recurrence::Monthly) => Some(Recurrence::Yearly),
+            Some(Recurrence::Yearly) => None,
+        }
+    }
+
+    fn prev(recurrence: Option<Self>) -> Option<Self> {
+        match recurrence {
+            None => Some(Recurrence::Yearly),
+            Some(Recurrence::Daily) => None,
+            Some(Recurrence::Weekly) => Some(Recurrence::Daily),
+            Some(Recurrence::Monthly) => Some(Recurrence::Weekly),
+            Some(Recurrence::Yearly) => Some(Recurrence::Monthly),
+        }
+    }
+
+    /// Next due date. Month arithmetic clamps to the end of shorter months (Jan 31 -> Feb 28).
+    fn advance(self, date: NaiveDate) -> NaiveDate {
+        let next = match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::Weekly => date.checked_add_days(Days::new(7)),
+            Recurrence::Monthly => date.checked_add_months(Months::new(1)),
+            Recurrence::Yearly => date.checked_add_months(Months::new(12)),
+        };
+        next.unwrap_or(date)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -36,42 +475,195 @@ struct TodoItem {
     id: usize,
     title: String,
     description: String,
-    target_date: NaiveDate,
+    // Deadline; tasks entered without a time are due at `date_utils::END_OF_DAY`
+    target_datetime: NaiveDateTime,
     created_date: NaiveDate,
     completed: bool,
+    // When the task was last marked done; None while pending and for tasks completed
+    // before this was recorded
+    #[serde(default)]
+    completed_date: Option<NaiveDate>,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+    // Free-form longer text, edited in the form or the Shift+O editor
+    #[serde(default)]
+    notes: String,
+    // Checklist steps, listed under the task in the table while it is expanded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    subtasks: Vec<SubTask>,
+    // Last time the task was edited, completed or reprioritized; None until then. Lets a
+    // merge of two copies of the list keep the newer version of a task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<NaiveDateTime>,
+    // Put away with `f`: still saved in the list, but only shown in the archive view and
+    // left out of the progress bar and statistics
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    archived: bool,
+    // Id of the follow-up created when this recurring task was completed, so completing it
+    // again after an uncomplete doesn't create a second one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_occurrence_id: Option<usize>,
+    // Keys this version doesn't know about, written back untouched
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl TodoItem {
-    fn new(id: usize, title: String, description: String, target_date: NaiveDate) -> Self {
+    fn new(id: usize, title: String, description: String, target_datetime: NaiveDateTime) -> Self {
         Self {
             id,
             title,
             description,
-            target_date,
+            target_datetime,
             created_date: Local::now().date_naive(),
             completed: false,
+            completed_date: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            recurrence: None,
+            notes: String::new(),
+            subtasks: Vec::new(),
+            modified: None,
+            archived: false,
+            next_occurrence_id: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// The follow-up task created when a recurring task is completed
+    fn next_occurrence(&self, id: usize) -> Option<TodoItem> {
+        let recurrence = self.recurrence?;
+        let mut next = TodoItem::new(
+            id,
+            self.title.clone(),
+            self.description.clone(),
+            recurrence
+                .advance(self.target_datetime.date())
+                .and_time(self.target_datetime.time()),
+        );
+        next.priority = self.priority;
+        next.tags = self.tags.clone();
+        next.recurrence = self.recurrence;
+        next.notes = self.notes.clone();
+        // The same steps, to be done again
+        next.subtasks = self
+            .subtasks
+            .iter()
+            .map(|subtask| SubTask { completed: false, ..subtask.clone() })
+            .collect();
+        Some(next)
+    }
+
+    fn touch(&mut self) {
+        self.modified = Some(Local::now().naive_local().trunc_subsecs(0));
+    }
+
+    /// Done and total subtasks
+    fn subtask_progress(&self) -> (usize, usize) {
+        let done = self.subtasks.iter().filter(|subtask| subtask.completed).count();
+        (done, self.subtasks.len())
+    }
+
+    fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// First line of the description, with an ellipsis when more lines follow
+    fn description_summary(&self) -> String {
+        let mut lines = self.description.lines();
+        let first = lines.next().unwrap_or_default();
+        if lines.next().is_some() {
+            format!("{} …", first)
+        } else {
+            first.to_string()
+        }
+    }
+
+    fn tag_spans(&self, theme: &Theme) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for tag in &self.tags {
+            if !spans.is_empty() {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(Span::styled(format!("#{}", tag), Style::default().fg(tag_color(tag, theme))));
+        }
+        spans
+    }
+
+    /// "(in 3 days)", "(2 days overdue)" and so on, relative to today
+    fn due_label(&self) -> String {
+        if self.completed {
+            return match self.completed_date {
+                Some(date) => format!("(completed on {})", date.format("%Y-%m-%d")),
+                None => "(completed)".to_string(),
+            };
+        }
+        let days = (self.target_datetime.date() - Local::now().date_naive()).num_days();
+        let plural = |n: i64| if n == 1 { "" } else { "s" };
+        match days {
+            0 if self.is_overdue() => "(overdue)".to_string(),
+            0 => "(due today)".to_string(),
+            d if d > 0 => format!("(in {} day{})", d, plural(d)),
+            d => format!("({} day{} overdue)", -d, plural(-d)),
         }
     }
 
     fn is_overdue(&self) -> bool {
-        !self.completed && Local::now().date_naive() > self.target_date
+        !self.completed && Local::now().naive_local() > self.target_datetime
+    }
+
+    /// Open and overdue, or due later today
+    fn is_due_by_today(&self) -> bool {
+        !self.completed && self.target_datetime.date() <= Local::now().date_naive()
+    }
+
+    /// Open and due within `warn_days` days, but not overdue yet
+    fn is_due_soon(&self, warn_days: u32) -> bool {
+        let days_left = (self.target_datetime.date() - Local::now().date_naive()).num_days();
+        !self.completed && !self.is_overdue() && (0..=i64::from(warn_days)).contains(&days_left)
+    }
+
+    /// Whole days `today` is past the target date; `None` for completed tasks and tasks
+    /// due on or after `today`
+    fn days_overdue(&self, today: NaiveDate) -> Option<i64> {
+        let days = (today - self.target_datetime.date()).num_days();
+        (!self.completed && days > 0).then_some(days)
+    }
+
+    /// Overdue by more than `SEVERELY_OVERDUE_DAYS` days
+    fn is_severely_overdue(&self) -> bool {
+        self.days_overdue(Local::now().date_naive())
+            .is_some_and(|days| days > SEVERELY_OVERDUE_DAYS)
     }
 
-    fn get_status_color(&self) -> Color {
+    fn get_status_color(&self, config: &Config) -> Color {
         if self.completed {
-            Color::Green
+            config.theme.completed_fg
+        } else if self.is_severely_overdue() {
+            config.theme.severe_overdue_fg
         } else if self.is_overdue() {
-            Color::Red
+            config.theme.overdue_fg
+        } else if self.priority == Priority::Critical {
+            config.theme.critical_fg
+        } else if self.is_due_soon(config.warn_days) {
+            config.theme.due_soon_fg
         } else {
-            Color::White
+            config.theme.pending_fg
         }
     }
 
-    fn get_row_style(&self) -> Style {
-        let color = self.get_status_color();
+    fn get_row_style(&self, config: &Config) -> Style {
+        let color = self.get_status_color(config);
         if self.completed {
             Style::default().fg(color).add_modifier(Modifier::DIM)
-        } else if self.is_overdue() {
+        } else if self.is_severely_overdue()
+            || self.priority == Priority::Critical
+            || self.is_due_soon(config.warn_days)
+        {
             Style::default().fg(color).add_modifier(Modifier::BOLD)
         } else {
             Style::default().fg(color)
@@ -79,11 +671,132 @@ impl TodoItem {
     }
 }
 
-#[derive(PartialEq)]
+/// One step of a task's checklist
+#[derive(Clone, Serialize, Deserialize)]
+struct SubTask {
+    id: usize,
+    title: String,
+    completed: bool,
+}
+
+/// A deleted task and the day it went into the trash
+#[derive(Clone, Serialize, Deserialize)]
+struct TrashedItem {
+    deleted_date: NaiveDate,
+    task: TodoItem,
+}
+
+/// Popup shown over everything until any key is pressed
+struct Notice {
+    title: &'static str,
+    message: String,
+}
+
+/// One-line message shown in the footer until the next keypress
+enum Status {
+    Info(String),
+    Error(String),
+}
+
+/// Returned by `save_tasks` when the file on disk is no longer the one we loaded or saved
+#[derive(Debug)]
+struct ExternalChange;
+
+impl fmt::Display for ExternalChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("file changed on disk")
+    }
+}
+
+impl Error for ExternalChange {}
+
+/// Snapshot of the task list taken just before a mutation
+struct UndoRecord {
+    items: Vec<TodoItem>,
+    trash: Vec<TrashedItem>,
+    next_id: usize,
+    // Task the mutation was about, re-selected after undoing
+    task_id: Option<usize>,
+}
+
+/// Undo and redo history, each capped at `UNDO_LIMIT` snapshots
+#[derive(Default)]
+struct UndoStack {
+    undo: Vec<UndoRecord>,
+    redo: Vec<UndoRecord>,
+}
+
+impl UndoStack {
+    fn push(&mut self, record: UndoRecord) {
+        // A fresh change makes the undone history unreachable
+        self.redo.clear();
+        Self::push_capped(&mut self.undo, record);
+    }
+
+    /// Swaps `current` for the previous snapshot, keeping `current` for redo
+    fn undo(&mut self, current: UndoRecord) -> Option<UndoRecord> {
+        let record = self.undo.pop()?;
+        Self::push_capped(&mut self.redo, current);
+        Some(record)
+    }
+
+    fn redo(&mut self, current: UndoRecord) -> Option<UndoRecord> {
+        let record = self.redo.pop()?;
+        Self::push_capped(&mut self.undo, current);
+        Some(record)
+    }
+
+    fn push_capped(stack: &mut Vec<UndoRecord>, record: UndoRecord) {
+        stack.push(record);
+        if stack.len() > UNDO_LIMIT {
+            stack.remove(0);
+        }
+    }
+}
+
+/// Totals shown on the statistics screen, from `App::compute_stats`
+struct Stats {
+    total: usize,
+    completed: usize,
+    overdue: usize,
+    // Open tasks due today or within the next six days
+    due_this_week: usize,
+    // Percent of tasks completed; 0 for an empty list
+    completion_rate: f64,
+    // Mean days from creation to completion; None until a task with a known completion
+    // date is done
+    avg_days_to_complete: Option<f64>,
+}
+
+/// A project whose lock is held by another instance, as reported by `lock::acquire`
+struct LockedProject {
+    path: PathBuf,
+    pid: Option<u32>,
+    running: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
 enum AppMode {
     Normal,
     AddTask,
     EditTask,
+    ConfirmDelete,
+    ConfirmReload,
+    ProjectSwitcher,
+    NewProject,
+    AddSubtask,
+    Search,
+    Import,
+    ConfirmImport,
+    ConfirmLock,
+    CalendarView,
+    ContextMenu,
+    Help,
+    ConfirmArchive,
+    ArchiveView,
+    TrashView,
+    NotesEditor,
+    Stats,
 }
 
 #[derive(Default)]
@@ -91,7 +804,16 @@ struct TaskForm {
     title: String,
     description: String,
     target_date: String,
-    field_index: usize, // 0: title, 1: description, 2: date
+    priority: Priority,
+    tags: String,
+    recurrence: Option<Recurrence>,
+    notes: String,
+    field_index: usize, // 0: title, 1: description, 2: date, 3: priority, 4: recurrence, 5: tags, 6: notes
+    // Cursor of each text field as a byte offset, indexed like `field_index`
+    cursors: [usize; 7],
+    error: Option<String>,
+    // Set while the "similar task exists" warning is up; submitting again adds the task anyway
+    duplicate_warned: bool,
 }
 
 impl TaskForm {
@@ -99,53 +821,309 @@ impl TaskForm {
         self.title.clear();
         self.description.clear();
         self.target_date.clear();
+        self.priority = Priority::default();
+        self.tags.clear();
+        self.recurrence = None;
+        self.notes.clear();
         self.field_index = 0;
+        self.cursors = [0; 7];
+        self.error = None;
+        self.duplicate_warned = false;
     }
 
-    fn current_field_mut(&mut self) -> &mut String {
-        match self.field_index {
+    /// Puts every cursor after the last character, for editing a filled-in form
+    fn move_cursors_to_end(&mut self) {
+        self.cursors = [
+            self.title.len(),
+            self.description.len(),
+            self.target_date.len(),
+            0,
+            0,
+            self.tags.len(),
+            self.notes.len(),
+        ];
+    }
+
+    // Priority and recurrence are picked with arrow keys rather than typed, so they have no text buffer
+    fn current_field_mut(&mut self) -> Option<(&mut String, &mut usize)> {
+        let field = match self.field_index {
             0 => &mut self.title,
             1 => &mut self.description,
             2 => &mut self.target_date,
-            _ => &mut self.title,
+            5 => &mut self.tags,
+            6 => &mut self.notes,
+            _ => return None,
+        };
+        let cursor = &mut self.cursors[self.field_index];
+        *cursor = (*cursor).min(field.len());
+        Some((field, cursor))
+    }
+
+    /// Where to draw the cursor in field `index`, if that field has the focus
+    fn cursor_in(&self, index: usize) -> Option<usize> {
+        (self.field_index == index).then_some(self.cursors[index])
+    }
+
+    fn insert_char(&mut self, c: char) {
+        if let Some((field, cursor)) = self.current_field_mut() {
+            field.insert(*cursor, c);
+            *cursor += c.len_utf8();
+        }
+    }
+
+    /// Inserts pasted text at the cursor. Line breaks become spaces outside the multi-line
+    /// description and notes.
+    fn insert_str(&mut self, text: &str) {
+        let text = if matches!(self.field_index, 1 | 6) {
+            text.replace("\r\n", "\n")
+        } else {
+            text.lines().collect::<Vec<_>>().join(" ")
+        };
+        if let Some((field, cursor)) = self.current_field_mut() {
+            field.insert_str(*cursor, &text);
+            *cursor += text.len();
+        }
+    }
+
+    fn delete_before_cursor(&mut self) {
+        if let Some((field, cursor)) = self.current_field_mut()
+            && let Some(prev) = field[..*cursor].chars().next_back()
+        {
+            *cursor -= prev.len_utf8();
+            field.remove(*cursor);
+        }
+    }
+
+    fn delete_at_cursor(&mut self) {
+        if let Some((field, cursor)) = self.current_field_mut()
+            && *cursor < field.len()
+        {
+            field.remove(*cursor);
+        }
+    }
+
+    fn move_cursor_left(&mut self) {
+        if let Some((field, cursor)) = self.current_field_mut()
+            && let Some(prev) = field[..*cursor].chars().next_back()
+        {
+            *cursor -= prev.len_utf8();
+        }
+    }
+
+    fn move_cursor_right(&mut self) {
+        if let Some((field, cursor)) = self.current_field_mut()
+            && let Some(next) = field[*cursor..].chars().next()
+        {
+            *cursor += next.len_utf8();
+        }
+    }
+
+    fn move_cursor_home(&mut self) {
+        if let Some((_, cursor)) = self.current_field_mut() {
+            *cursor = 0;
+        }
+    }
+
+    fn move_cursor_end(&mut self) {
+        if let Some((field, cursor)) = self.current_field_mut() {
+            *cursor = field.len();
         }
     }
 
     fn next_field(&mut self) {
-        self.field_index = (self.field_index + 1) % 3;
+        self.field_index = (self.field_index + 1) % 7;
     }
 
     fn prev_field(&mut self) {
-        self.field_index = if self.field_index == 0 { 2 } else { self.field_index - 1 };
+        self.field_index = if self.field_index == 0 { 6 } else { self.field_index - 1 };
+    }
+
+    fn parsed_tags(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches('#').trim())
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect()
     }
 }
 
 struct App {
+    save_path: PathBuf,
+    backup_count: usize,
+    // Preferences from the config file, shared by every project
+    config: Config,
     state: TableState,
     items: Vec<TodoItem>,
+    // Deleted tasks, oldest first, and the trash view's cursor
+    trash: Vec<TrashedItem>,
+    trash_state: TableState,
+    // Raw entries from the save file that failed to decode; written back untouched on save
+    skipped_items: Vec<serde_json::Value>,
     scroll_state: ScrollbarState,
     mode: AppMode,
     form: TaskForm,
         sort_mode: SortMode,
+        sort_ascending: bool,
+        // Task ids in the order they were arranged by hand, used while sorting manually
+        manual_order: Vec<usize>,
+        column_widths: ColumnWidths,
+        // Column resized by Shift+←/→, picked with ←/→ and underlined in the header
+        focused_column: usize,
         next_id: usize,
         edit_id: Option<usize>,
+        search_query: Option<String>,
+        // Only tasks due on this day are shown, picked in the calendar view
+        date_filter: Option<NaiveDate>,
+        // Completed tasks are left out of the table (but still counted in the progress bar)
+        hide_completed: bool,
+        // Tasks whose subtasks are listed under them in the table, by id
+        expanded: HashSet<usize>,
+        subtask_input: String,
+        // Day highlighted in the calendar view; the calendar shows its month
+        calendar_day: NaiveDate,
+        // The form's F2 date picker is open over the form, highlighting `calendar_day`
+        date_picker_open: bool,
+        selected_ids: HashSet<usize>,
+        // Id of the task the delete popup asks about, None when it asks about the marked ones.
+        // Held by id so a reload while the popup is open can't change which task goes.
+        pending_delete: Option<usize>,
+        status: Option<Status>,
+        notice: Option<Notice>,
+        undo_stack: UndoStack,
+        import_path: String,
+        // Tasks read from `import_path`, waiting for the import to be confirmed
+        import_preview: Option<import::ImportResult>,
+        // Project that another instance has locked, waiting for a choice of how to open it
+        locked_project: Option<LockedProject>,
+        // Held while this instance may write the save file; dropped when switching projects
+        _file_lock: Option<lock::FileLock>,
+        // Another instance holds the lock; nothing is written to disk
+        read_only: bool,
+        // Hash of the save file as we last read or wrote it, None if it didn't exist
+        disk_hash: std::cell::Cell<Option<u64>>,
+        // A save found the file changed on disk; the conflict popup is waiting for a choice
+        save_conflict: bool,
+        // Saving on quit failed; the next q or Esc quits without saving
+        quit_unsaved: bool,
+        // The list has changes that aren't on disk yet, either waiting for autosave or
+        // because the last save failed
+        dirty: bool,
+        last_save: Instant,
+        // Whether task changes are appended to the change log (a per-list setting), and the
+        // list as last logged; None while logging is off or impossible
+        log_changes: bool,
+        change_log_baseline: Option<Vec<TodoItem>>,
+        // Write JSON on one line instead of indented (a per-list setting)
+        compact_json: bool,
+        // Passphrase-derived key for reading encrypted files, and whether saves are encrypted
+        cipher: Option<crypto::Cipher>,
+        encrypt_on_save: bool,
+        // Name of the open list, the save file's name without its extension
+        current_project: String,
+        // Directory holding the project files, the one the save file lives in
+        project_dir: PathBuf,
+        // Project files shown in the switcher, and the highlighted one
+        projects: Vec<PathBuf>,
+        project_selected: usize,
+        project_name: String,
+        // Text being edited in the full-screen notes editor, for the task in `edit_id`
+        notes_input: String,
+        // Archived tasks as last read for the archive view, and its cursor
+        archive_items: Vec<TodoItem>,
+        archive_state: TableState,
+        // Where the last frame put the whole screen and the table (header row included),
+        // for mapping mouse clicks to rows
+        screen_area: Rect,
+        table_area: Rect,
+        // Visible row and time of the last left click, to detect double clicks
+        last_click: Option<(usize, Instant)>,
+        // Position of the open right-click menu and its highlighted entry
+        context_menu_area: Rect,
+        context_menu_selected: usize,
 }
 
 impl App {
-    fn new() -> Self {
+    /// Opens `save_path`; without a `file_lock` the list is read-only. `cipher` must be
+    /// given to read an encrypted file and is required for `encrypt_on_save`.
+    fn new(
+        save_path: PathBuf,
+        backup_count: usize,
+        file_lock: Option<lock::FileLock>,
+        cipher: Option<crypto::Cipher>,
+        encrypt_on_save: bool,
+        config: Config,
+    ) -> Result<Self> {
+        let current_project = storage::project_name(&save_path);
+        let project_dir = match save_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
         let mut app = Self {
+            save_path,
+            backup_count,
+            config,
             state: TableState::default().with_selected(0),
             items: Vec::new(),
+            trash: Vec::new(),
+            trash_state: TableState::default(),
+            skipped_items: Vec::new(),
             scroll_state: ScrollbarState::new(0),
             mode: AppMode::Normal,
             form: TaskForm::default(),
                 sort_mode: SortMode::CreatedDate,
+                sort_ascending: true,
+                manual_order: Vec::new(),
+                column_widths: ColumnWidths::default(),
+                focused_column: 0,
                 next_id: 1,
                 edit_id: None,
+                search_query: None,
+                date_filter: None,
+                hide_completed: false,
+                expanded: HashSet::new(),
+                subtask_input: String::new(),
+                calendar_day: Local::now().date_naive(),
+                date_picker_open: false,
+                selected_ids: HashSet::new(),
+                pending_delete: None,
+                status: None,
+                notice: None,
+                undo_stack: UndoStack::default(),
+                import_path: String::new(),
+                import_preview: None,
+                locked_project: None,
+                read_only: file_lock.is_none(),
+                _file_lock: file_lock,
+                disk_hash: std::cell::Cell::new(None),
+                save_conflict: false,
+                quit_unsaved: false,
+                dirty: false,
+                last_save: Instant::now(),
+                log_changes: false,
+                compact_json: false,
+                change_log_baseline: None,
+                encrypt_on_save: encrypt_on_save && cipher.is_some(),
+                cipher,
+                current_project,
+                project_dir,
+                projects: Vec::new(),
+                project_selected: 0,
+                project_name: String::new(),
+                notes_input: String::new(),
+                archive_items: Vec::new(),
+                archive_state: TableState::default(),
+                screen_area: Rect::default(),
+                table_area: Rect::default(),
+                last_click: None,
+                context_menu_area: Rect::default(),
+                context_menu_selected: 0,
         };
 
         // Load tasks from file
-        app.load_tasks();
+        app.load_tasks()?;
+        app.reset_change_log();
+        app.purge_old_trash();
         app.update_scroll_state();
 
         // If no tasks loaded and file doesn't exist, start with empty list
@@ -153,391 +1131,4569 @@ impl App {
             app.next_id = 1;
         }
 
-        app
+        Ok(app)
     }
 
-    fn load_tasks(&mut self) {
-        if Path::new(SAVE_FILE).exists() {
-            match fs::read_to_string(SAVE_FILE) {
+    fn load_tasks(&mut self) -> Result<()> {
+        if self.save_path.exists() {
+            // A file that can't be read at all is reported rather than replaced by an empty list
+            let bytes = fs::read(&self.save_path)
+                .wrap_err_with(|| format!("failed to read {}", self.save_path.display()))?;
+            self.disk_hash.set(Some(storage::content_hash(&bytes)));
+            let parsed = match String::from_utf8(bytes) {
                 Ok(content) => {
-                    match serde_json::from_str::<Vec<TodoItem>>(&content) {
-                        Ok(tasks) => {
-                            self.items = tasks;
-                            // Set next_id to be higher than any existing id
-                            self.next_id = self.items.iter().map(|item| item.id).max().unwrap_or(0) + 1;
-                        }
-                        Err(_) => {
-                            // If JSON is corrupted, start fresh
-                            self.items = Vec::new();
-                            self.next_id = 1;
-                        }
-                    }
+                    // A wrong passphrase mustn't be mistaken for corruption and overwritten
+                    let content = self.decode(content).map_err(|err| {
+                        eyre!("failed to decrypt {}: {}", self.save_path.display(), err)
+                    })?;
+                    storage::parse_state(&content, StorageFormat::from_path(&self.save_path))
+                }
+                Err(err) => Err(ParseError::Invalid(err.to_string())),
+            };
+            match parsed {
+                Ok(state) => {
+                    let source = self.save_path.clone();
+                    self.apply_state(state, &source);
                 }
-                Err(_) => {
-                    // If can't read file, start fresh
+                Err(ParseError::UnsupportedVersion(version)) => {
+                    // Starting with an empty list would overwrite the newer data on the next save
+                    return Err(eyre!(
+                        "{} uses schema version {}, but this rtodo only understands up to version {}; please upgrade",
+                        self.save_path.display(),
+                        version,
+                        storage::CURRENT_VERSION
+                    ));
+                }
+                Err(err) => {
+                    // If the file is corrupted, keep a copy of it before starting fresh so
+                    // the next save can't destroy whatever is still recoverable
+                    let backup = storage::corrupt_backup_path(&self.save_path);
+                    fs::copy(&self.save_path, &backup).wrap_err_with(|| {
+                        format!(
+                            "{} is corrupted and could not be backed up to {}",
+                            self.save_path.display(),
+                            backup.display()
+                        )
+                    })?;
+                    // Fall back to the newest rotating backup before giving up on the tasks
+                    let newest = storage::backup_path(&self.save_path, 1);
+                    if let Ok(state) = self.read_backup(&newest) {
+                        self.apply_state(state, &newest);
+                        // Write the recovered list back over the broken file
+                        self.dirty = true;
+                        self.notice = Some(Notice {
+                            title: "Warning",
+                            message: format!(
+                                "{} could not be read ({}).\n\nA copy was saved to {} and the tasks were loaded from {} instead.",
+                                self.save_path.display(),
+                                err,
+                                backup.display(),
+                                newest.display()
+                            ),
+                        });
+                        return Ok(());
+                    }
+                    self.notice = Some(Notice {
+                        title: "Warning",
+                        message: format!(
+                            "{} could not be read ({}).\n\nA copy was saved to {} and rtodo started with an empty list.",
+                            self.save_path.display(),
+                            err,
+                            backup.display()
+                        ),
+                    });
                     self.items = Vec::new();
                     self.next_id = 1;
                 }
             }
         }
+        Ok(())
     }
 
-    fn save_tasks(&self) {
-        if let Ok(json) = serde_json::to_string_pretty(&self.items) {
-            let _ = fs::write(SAVE_FILE, json);
+    /// Replaces the task list with the contents of a parsed save file read from `source`
+    fn apply_state(&mut self, state: AppState, source: &Path) {
+        self.items.clear();
+        self.skipped_items.clear();
+        // Unlike tasks, a trash entry that doesn't decode isn't worth a warning; it is dropped
+        self.trash = state
+            .trash
+            .into_iter()
+            .filter_map(|value| serde_json::from_value(value).ok())
+            .collect();
+        self.clamp_trash_selection();
+        for value in state.items {
+            match serde_json::from_value::<TodoItem>(value.clone()) {
+                Ok(item) => self.items.push(item),
+                Err(_) => self.skipped_items.push(value),
+            }
+        }
+        if !self.skipped_items.is_empty() {
+            self.notice = Some(Notice {
+                title: "Warning",
+                message: format!(
+                    "{} {} could not be loaded from {}.\n\nThey are kept unchanged in the file, so you can fix them by hand.",
+                    self.skipped_items.len(),
+                    if self.skipped_items.len() == 1 { "task" } else { "tasks" },
+                    source.display()
+                ),
+            });
         }
+        self.sort_mode = state.settings.sort_mode;
+        self.manual_order = state.settings.manual_order;
+        self.sort_ascending = state.settings.sort_ascending;
+        self.log_changes = state.settings.change_log;
+        self.compact_json = state.settings.compact_json;
+        self.column_widths = Some(state.settings.column_widths)
+            .filter(|widths| widths.is_valid())
+            .unwrap_or_default();
+        self.sort_items();
+        self.state.select(state.settings.selected);
+        self.clamp_selection();
+        // Set next_id to be higher than any existing id, including ids of
+        // entries we couldn't decode so they never collide once fixed
+        let skipped_ids = self
+            .skipped_items
+            .iter()
+            .filter_map(|value| value.get("id")?.as_u64())
+            .map(|id| id as usize);
+        let next_unused = self
+            .items
+            .iter()
+            .map(|item| item.id)
+            .chain(skipped_ids)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.next_id = next_unused.max(state.next_id);
     }
 
-    fn update_scroll_state(&mut self) {
-        self.scroll_state = ScrollbarState::new(self.items.len());
-    }
+    fn save_tasks(&self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        let plaintext = self.serialize_tasks()?;
 
-    fn sort_items(&mut self) {
-        match self.sort_mode {
-            SortMode::CreatedDate => {
-                self.items.sort_by(|a, b| b.created_date.cmp(&a.created_date));
-            }
-            SortMode::TargetDate => {
-                self.items.sort_by(|a, b| a.target_date.cmp(&b.target_date));
+        match fs::read(&self.save_path) {
+            Ok(existing) => {
+                let hash = storage::content_hash(&existing);
+                let existing = String::from_utf8(existing).ok();
+                let was_encrypted = existing.as_deref().is_some_and(crypto::is_encrypted);
+                // Identical saves are skipped so they don't push real history out of the backups.
+                // Encrypted files differ on every save, so they are compared decrypted.
+                if let Some(existing) = existing
+                    && was_encrypted == self.encrypt_on_save
+                    && self.decode(existing).is_ok_and(|existing| existing == plaintext)
+                {
+                    self.disk_hash.set(Some(hash));
+                    return Ok(());
+                }
+                if self.disk_hash.get() != Some(hash) {
+                    return Err(io::Error::other(ExternalChange));
+                }
+                storage::rotate_backups(&self.save_path, self.backup_count)?;
+                if self.encrypt_on_save && !was_encrypted {
+                    self.encrypt_copies()?;
+                }
             }
-            SortMode::Completion => {
-                self.items.sort_by(|a, b| a.completed.cmp(&b.completed));
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            // Writing over a file we couldn't read could destroy it
+            Err(err) => return Err(err),
+        }
+        let content = self.encode(plaintext)?;
+        storage::write_atomically(&self.save_path, &content)?;
+        self.disk_hash.set(Some(storage::content_hash(&content)));
+        Ok(())
+    }
+
+    /// Encrypts the backups, corrupt copies, archive and conflict copy of a list that was
+    /// plaintext until now and deletes its change log, so no readable copy of the list is
+    /// left beside the file once it is encrypted
+    fn encrypt_copies(&self) -> io::Result<()> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(());
+        };
+        let others = [storage::archive_path(&self.save_path), storage::conflict_path(&self.save_path)];
+        for path in storage::copies(&self.save_path).into_iter().chain(others) {
+            let content = match fs::read(&path) {
+                Ok(content) => content,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            match String::from_utf8(content) {
+                Ok(content) if crypto::is_encrypted(&content) => {}
+                Ok(content) => {
+                    let sealed = cipher.encrypt(&content).map_err(io::Error::other)?;
+                    storage::write_atomically(&path, &sealed)?;
+                }
+                // Only text can be sealed, and a copy that isn't text can't be loaded anyway
+                Err(_) => fs::remove_file(&path)?,
             }
         }
+        match fs::remove_file(changelog::log_path(&self.save_path)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
     }
 
-    fn next_row(&mut self) {
-        if self.items.is_empty() {
-            return;
+    /// Plaintext of a save file or backup, decrypting it if needed
+    fn decode(&self, content: String) -> Result<String, String> {
+        if !crypto::is_encrypted(&content) {
+            return Ok(content);
+        }
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(&content).map_err(|err| err.to_string()),
+            None => Err("file is encrypted".to_string()),
         }
-        let i = match self.state.selected() {
-            Some(i) => (i + 1) % self.items.len(),
-            None => 0,
-        };
-        self.state.select(Some(i));
     }
 
-    fn previous_row(&mut self) {
-        if self.items.is_empty() {
-            return;
+    /// What to write to disk for `plaintext`
+    fn encode(&self, plaintext: String) -> io::Result<String> {
+        match &self.cipher {
+            Some(cipher) if self.encrypt_on_save => cipher.encrypt(&plaintext).map_err(io::Error::other),
+            _ => Ok(plaintext),
         }
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.items.len() - 1
+    }
+
+    fn serialize_tasks(&self) -> io::Result<String> {
+        // Stored by id rather than in display order, so changing the sort doesn't rewrite
+        // every task and the file diffs cleanly under version control
+        let mut by_id: Vec<&TodoItem> = self.items.iter().collect();
+        by_id.sort_by_key(|item| item.id);
+        let items = by_id
+            .into_iter()
+            .map(serde_json::to_value)
+            .chain(self.skipped_items.iter().cloned().map(Ok))
+            .collect::<serde_json::Result<Vec<_>>>();
+        let state = AppState {
+            version: storage::CURRENT_VERSION,
+            settings: Settings {
+                sort_mode: self.sort_mode,
+                sort_ascending: self.sort_ascending,
+                column_widths: self.column_widths,
+                change_log: self.log_changes,
+                compact_json: self.compact_json,
+                selected: self.selected_index(),
+                manual_order: if self.sort_mode == SortMode::Manual {
+                    self.items.iter().map(|item| item.id).collect()
                 } else {
-                    i - 1
-                }
-            }
-            None => 0,
+                    Vec::new()
+                },
+            },
+            next_id: self.next_id,
+            items: items?,
+            trash: self
+                .trash
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<_>>()?,
         };
-        self.state.select(Some(i));
+        storage::serialize_state(&state, StorageFormat::from_path(&self.save_path))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
     }
 
-    fn toggle_completed(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if let Some(item) = self.items.get_mut(selected) {
-                item.completed = !item.completed;
-                self.save_tasks(); // Save after toggling completion
+    /// Replaces the task list with the save file as it is on disk now, keeping the current
+    /// sort and selected task. The in-memory list stays reachable through undo.
+    fn reload_from_disk(&mut self) {
+        let format = StorageFormat::from_path(&self.save_path);
+        let loaded = fs::read_to_string(&self.save_path)
+            .map_err(|err| err.kind().to_string())
+            .and_then(|content| {
+                let plaintext = self.decode(content.clone())?;
+                let state = storage::parse_state(&plaintext, format).map_err(|err| err.to_string())?;
+                Ok((content, state))
+            });
+        let (content, state) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                self.status = Some(Status::Error(format!(
+                    "Failed to reload: {} ({})",
+                    err,
+                    self.save_path.display()
+                )));
+                return;
             }
+        };
+
+        let selected = self.selected_id();
+        let (sort_mode, sort_ascending) = (self.sort_mode, self.sort_ascending);
+        let column_widths = self.column_widths;
+        self.push_undo(selected);
+        let source = self.save_path.clone();
+        self.apply_state(state, &source);
+        self.reset_change_log();
+        self.disk_hash.set(Some(storage::content_hash(&content)));
+        self.dirty = false;
+        self.sort_mode = sort_mode;
+        self.sort_ascending = sort_ascending;
+        self.column_widths = column_widths;
+        self.sort_items();
+        self.selected_ids
+            .retain(|id| self.items.iter().any(|item| item.id == *id));
+        match selected {
+            Some(id) => self.select_task(id),
+            None => self.clamp_selection(),
         }
+        self.update_scroll_state();
+        self.status = Some(Status::Info("Reloaded from disk".to_string()));
     }
 
-    fn delete_selected(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if selected < self.items.len() {
-                self.items.remove(selected);
-                if self.items.is_empty() {
-                    self.state.select(None);
-                } else if selected >= self.items.len() {
-                    self.state.select(Some(self.items.len() - 1));
-                }
-                self.update_scroll_state();
-                self.save_tasks(); // Save after deletion
-            }
+    /// Re-reads the save file, asking first if that would throw away changes that never
+    /// made it to disk
+    fn start_reload(&mut self) {
+        self.flush();
+        if self.save_conflict {
+            return;
+        }
+        if self.dirty {
+            self.mode = AppMode::ConfirmReload;
+        } else {
+            self.reload_from_disk();
         }
     }
 
-    fn start_add_task(&mut self) {
-        self.mode = AppMode::AddTask;
-        self.form.clear();
-        self.edit_id = None;
+    /// Reloads after another program rewrote the save file. Notifications for our own saves
+    /// are ignored because the content still matches what we wrote.
+    #[cfg(feature = "watch")]
+    fn reload_external_change(&mut self) {
+        if self.save_conflict {
+            return;
+        }
+        if self.dirty {
+            // Saving runs into the change and asks what to do, instead of losing our edits
+            self.flush();
+            return;
+        }
+        let Ok(content) = fs::read_to_string(&self.save_path) else {
+            return;
+        };
+        if self.disk_hash.get() == Some(storage::content_hash(&content)) {
+            return;
+        }
+        self.reload_from_disk();
+        if !matches!(self.status, Some(Status::Error(_))) {
+            self.status = Some(Status::Info("Reloaded (external change)".to_string()));
+        }
     }
 
-    fn start_edit_task(&mut self) {
-        if let Some(selected) = self.state.selected() {
-            if let Some(item) = self.items.get(selected) {
-                self.mode = AppMode::EditTask;
-                self.edit_id = Some(item.id);
-                self.form.title = item.title.clone();
-                self.form.description = item.description.clone();
-                self.form.target_date = item.target_date.format("%Y-%m-%d").to_string();
-                self.form.field_index = 0;
-            }
-        }
-    }
-
-    fn submit_form(&mut self) {
-        if let Ok(target_date) = NaiveDate::parse_from_str(&self.form.target_date, "%Y-%m-%d") {
-            match self.mode {
-                AppMode::AddTask => {
-                    let item = TodoItem::new(
-                        self.next_id,
-                        self.form.title.clone(),
-                                             self.form.description.clone(),
-                                             target_date,
-                    );
-                    self.items.push(item);
-                    self.next_id += 1;
-                    self.update_scroll_state();
-                    self.save_tasks(); // Save after adding
-                }
-                AppMode::EditTask => {
-                    if let Some(edit_id) = self.edit_id {
-                        if let Some(item) = self.items.iter_mut().find(|i| i.id == edit_id) {
-                            item.title = self.form.title.clone();
-                            item.description = self.form.description.clone();
-                            item.target_date = target_date;
-                            self.save_tasks(); // Save after editing
-                        }
+    /// Settles a save conflict: `r` reloads the file from disk, `o` overwrites it with our
+    /// list, and `c` writes our list to `<file>.conflict` before reloading
+    fn resolve_conflict(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('r') | KeyCode::Char('R') => self.reload_from_disk(),
+            KeyCode::Char('o') | KeyCode::Char('O') => {
+                let on_disk = fs::read_to_string(&self.save_path).ok();
+                self.disk_hash.set(on_disk.as_deref().map(storage::content_hash));
+                self.report_save(self.save_tasks());
+            }
+            KeyCode::Char('c') | KeyCode::Char('C') => {
+                let conflict = storage::conflict_path(&self.save_path);
+                match self
+                    .serialize_tasks()
+                    .and_then(|plaintext| self.encode(plaintext))
+                    .and_then(|content| storage::write_atomically(&conflict, &content))
+                {
+                    Ok(()) => {
+                        self.reload_from_disk();
+                        self.notice = Some(Notice {
+                            title: "Saved copy",
+                            message: format!(
+                                "Your changes were saved to {} and the list was reloaded from disk.",
+                                conflict.display()
+                            ),
+                        });
+                    }
+                    Err(err) => {
+                        self.status = Some(Status::Error(format!(
+                            "Failed to save: {} ({})",
+                            err.kind(),
+                            conflict.display()
+                        )));
                     }
                 }
-                _ => {}
             }
-            self.sort_items();
+            KeyCode::Esc => {
+                self.status = Some(Status::Error("Not saved: file changed on disk".to_string()));
+            }
+            // Keep asking until one of the choices is picked
+            _ => return,
         }
-        self.mode = AppMode::Normal;
+        self.save_conflict = false;
     }
 
-    fn cancel_form(&mut self) {
-        self.mode = AppMode::Normal;
-        self.form.clear();
-        self.edit_id = None;
+    /// Replaces the task list with the most recent rotating backup. The current list stays
+    /// reachable through undo and becomes the newest backup on the following save.
+    fn restore_backup(&mut self) {
+        let backup = storage::backup_path(&self.save_path, 1);
+        match self.read_backup(&backup) {
+            Ok(state) => {
+                self.push_undo(self.selected_id());
+                self.apply_state(state, &backup);
+                self.selected_ids.clear();
+                self.update_scroll_state();
+                self.mark_dirty();
+                self.status = Some(Status::Info(format!("Restored {}", backup.display())));
+            }
+            Err(err) => {
+                self.status = Some(Status::Error(format!(
+                    "Failed to restore backup: {} ({})",
+                    err,
+                    backup.display()
+                )));
+            }
+        }
     }
 
-    fn get_progress(&self) -> (usize, usize) {
-        let completed = self.items.iter().filter(|item| item.completed).count();
-        let total = self.items.len();
-        (completed, total)
+    /// Merges the todo file `other` into this list on the command line, printing what it
+    /// would do and asking before saving unless `yes` is set
+    fn merge_file(&mut self, other: &Path, yes: bool) -> Result<()> {
+        if self.read_only {
+            return Err(eyre!("{} is open in another rtodo", self.save_path.display()));
+        }
+        let content = fs::read_to_string(other)
+            .wrap_err_with(|| format!("failed to read {}", other.display()))?;
+        let content = self
+            .decode(content)
+            .map_err(|err| eyre!("failed to decrypt {}: {}", other.display(), err))?;
+        let state = storage::parse_state(&content, StorageFormat::from_path(other))
+            .map_err(|err| eyre!("failed to parse {}: {}", other.display(), err))?;
+        let total = state.items.len();
+        let theirs: Vec<TodoItem> = state
+            .items
+            .into_iter()
+            .filter_map(|value| serde_json::from_value(value).ok())
+            .collect();
+        let malformed = total - theirs.len();
+
+        let mut items = self.items.clone();
+        let mut next_id = self.next_id;
+        let trash: Vec<&TodoItem> = self.trash.iter().map(|entry| &entry.task).collect();
+        let report = merge::merge(&mut items, &trash, theirs, &mut next_id);
+        println!("Merging {} into {}: {}", other.display(), self.save_path.display(), report.summary());
+        for (marker, titles, note) in [
+            ("+", &report.added, ""),
+            ("~", &report.updated, ""),
+            ("=", &report.kept, " (local copy is newer)"),
+            ("!", &report.conflicts, " (changed in both, kept local copy)"),
+            ("-", &report.trashed, " (deleted here, left in the trash)"),
+        ] {
+            for title in titles {
+                println!("  {} {}{}", marker, title, note);
+            }
+        }
+        if malformed > 0 {
+            println!("{} tasks in {} could not be read and were skipped.", malformed, other.display());
+        }
+        if report.added.is_empty() && report.updated.is_empty() {
+            println!("Nothing to merge.");
+            return Ok(());
+        }
+        if !yes {
+            print!("Apply these changes? [y/N] ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                println!("Left {} untouched.", self.save_path.display());
+                return Ok(());
+            }
+        }
+
+        self.items = items;
+        self.next_id = next_id;
+        self.sort_items();
+        self.mark_dirty();
+        self.flush();
+        if self.dirty {
+            return Err(eyre!("failed to save {}", self.save_path.display()));
+        }
+        println!("Merged into {}.", self.save_path.display());
+        Ok(())
     }
 
-    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        loop {
-            terminal.draw(|frame| self.draw(frame))?;
+    /// Adds the tasks still readable in the damaged file `source` that aren't in the list
+    /// already, and reports what was found
+    fn recover_from(&mut self, source: &Path) -> Result<()> {
+        if self.read_only {
+            return Err(eyre!("{} is open in another rtodo", self.save_path.display()));
+        }
+        let content = fs::read_to_string(source)
+            .wrap_err_with(|| format!("failed to read {}", source.display()))?;
+        let content = self
+            .decode(content)
+            .map_err(|err| eyre!("failed to decrypt {}: {}", source.display(), err))?;
+        let (state, complete) = storage::salvage_state(&content)
+            .map_err(|err| eyre!("nothing to recover from {}: {}", source.display(), err))?;
 
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match self.mode {
-                        AppMode::Normal => {
-                            match key.code {
-                                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-                                KeyCode::Down => self.next_row(),
-                                KeyCode::Up => self.previous_row(),
-                                KeyCode::Char(' ') => self.toggle_completed(),
-                                KeyCode::Char('n') | KeyCode::Char('N') => self.start_add_task(),
-                                KeyCode::Char('e') | KeyCode::Char('E') => self.start_edit_task(),
-                                KeyCode::Char('d') | KeyCode::Char('D') => self.delete_selected(),
-                                KeyCode::Char('s') | KeyCode::Char('S') => {
-                                    self.sort_mode = SortMode::CreatedDate;
-                                    self.sort_items();
-                                }
-                                KeyCode::Char('t') | KeyCode::Char('T') => {
-                                    self.sort_mode = SortMode::TargetDate;
-                                    self.sort_items();
-                                }
-                                KeyCode::Char('c') | KeyCode::Char('C') => {
-                                    self.sort_mode = SortMode::Completion;
-                                    self.sort_items();
-                                }
-                                _ => {}
-                            }
-                        }
-                        AppMode::AddTask | AppMode::EditTask => {
-                            match key.code {
-                                KeyCode::Esc => self.cancel_form(),
-                                KeyCode::Enter => self.submit_form(),
-                                KeyCode::Tab => self.form.next_field(),
-                                KeyCode::BackTab => self.form.prev_field(),
-                                KeyCode::Char(c) => {
-                                    self.form.current_field_mut().push(c);
-                                }
-                                KeyCode::Backspace => {
-                                    self.form.current_field_mut().pop();
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
+        self.push_undo(self.selected_id());
+        let (mut recovered, mut duplicates, mut malformed) = (0, 0, 0);
+        for value in state.items {
+            match serde_json::from_value::<TodoItem>(value) {
+                Ok(item) if self.items.iter().any(|existing| existing.id == item.id) => duplicates += 1,
+                Ok(item) => {
+                    self.next_id = self.next_id.max(item.id + 1);
+                    self.items.push(item);
+                    recovered += 1;
                 }
+                Err(_) => malformed += 1,
             }
         }
+        self.sort_items();
+        self.clamp_selection();
+        self.update_scroll_state();
+        self.mark_dirty();
+
+        let mut message = format!(
+            "Recovered {} {} from {}.",
+            recovered,
+            if recovered == 1 { "task" } else { "tasks" },
+            source.display()
+        );
+        if duplicates > 0 {
+            message.push_str(&format!("\n{} were already in the list.", duplicates));
+        }
+        if malformed > 0 {
+            message.push_str(&format!("\n{} could not be decoded and were skipped.", malformed));
+        }
+        if !complete {
+            message.push_str("\nThe file ends early or is damaged part way through; tasks after that point are lost.");
+        }
+        self.notice = Some(Notice { title: "Recovery", message });
+        Ok(())
     }
 
-    fn draw(&mut self, frame: &mut Frame) {
-        let main_layout = Layout::vertical([
-            Constraint::Length(3), // Progress bar
-                                           Constraint::Min(5),    // Table
-                                           Constraint::Length(5), // Footer
-        ]);
-        let chunks = main_layout.split(frame.area());
+    fn read_backup(&self, backup: &Path) -> Result<AppState, String> {
+        let format = StorageFormat::from_path(&self.save_path);
+        fs::read_to_string(backup)
+            .map_err(|err| err.kind().to_string())
+            .and_then(|content| self.decode(content))
+            .and_then(|content| storage::parse_state(&content, format).map_err(|err| err.to_string()))
+    }
 
-        self.render_progress_bar(frame, chunks[0]);
-        self.render_table(frame, chunks[1]);
-        self.render_footer(frame, chunks[2]);
+    /// Schedules a save; `autosave` writes it out shortly after
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.record_changes();
+    }
 
-        if self.mode == AppMode::AddTask || self.mode == AppMode::EditTask {
-            self.render_form_popup(frame);
+    /// Starts logging from the list as it is now. Encrypted lists aren't logged, since the
+    /// log would give their contents away.
+    fn reset_change_log(&mut self) {
+        self.change_log_baseline = None;
+        if !self.log_changes || self.read_only {
+            return;
+        }
+        if self.encrypt_on_save {
+            self.status = Some(Status::Info("The change log is off for encrypted lists".to_string()));
+            return;
         }
+        self.change_log_baseline = Some(self.items.clone());
     }
 
-    fn render_progress_bar(&self, frame: &mut Frame, area: Rect) {
-        let (completed, total) = self.get_progress();
-        let progress = if total > 0 { completed as f64 / total as f64 } else { 0.0 };
+    /// Appends whatever changed since the last call to the change log
+    fn record_changes(&mut self) {
+        let Some(baseline) = &self.change_log_baseline else {
+            return;
+        };
+        let events = changelog::diff(baseline, &self.items, Local::now().naive_local().trunc_subsecs(0));
+        if events.is_empty() {
+            return;
+        }
+        let path = changelog::log_path(&self.save_path);
+        if let Err(err) = changelog::append(&path, &events) {
+            self.status = Some(Status::Error(format!(
+                "Failed to write change log: {} ({})",
+                err.kind(),
+                path.display()
+            )));
+        }
+        self.change_log_baseline = Some(self.items.clone());
+    }
 
-        let progress_text = format!("Progress: {}/{} tasks completed", completed, total);
-        let gauge = Gauge::default()
-        .block(Block::bordered().title("Todo Progress"))
-        .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
-        .percent((progress * 100.0) as u16)
-        .label(progress_text);
+    fn show_history(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let path = changelog::log_path(&self.save_path);
+        let message = match changelog::history(&path, id) {
+            Ok(events) if events.is_empty() && !self.log_changes => {
+                "No history: the change log is off for this list (start rtodo with --change-log true)"
+                    .to_string()
+            }
+            Ok(events) if events.is_empty() => "No changes logged for this task yet".to_string(),
+            Ok(events) => events
+                .iter()
+                .map(|event| event.summary())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(err) => format!("Failed to read {}: {}", path.display(), err.kind()),
+        };
+        self.notice = Some(Notice { title: "History", message });
+    }
 
-        frame.render_widget(gauge, area);
+    /// Writes pending changes now
+    fn flush(&mut self) {
+        if self.dirty && !self.save_conflict {
+            self.last_save = Instant::now();
+            self.report_save(self.save_tasks());
+        }
     }
 
-    fn render_table(&mut self, frame: &mut Frame, area: Rect) {
-        let header = ["Title", "Description", "Target Date", "Status"]
-        .into_iter()
-        .map(Cell::from)
-        .collect::<Row>()
-        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
-        .height(1);
+    fn autosave(&mut self) {
+        if self.last_save.elapsed() >= AUTOSAVE_INTERVAL {
+            self.flush();
+        }
+    }
 
-        let rows = self.items.iter().map(|item| {
-            let status = if item.completed { "✓ Done" } else { "○ Pending" };
-            let status_color = item.get_status_color();
+    /// Shows the outcome of a save in the status line
+    /// Saves before quitting, also so the selection is restored next time. `true` once the
+    /// list is on disk, or when `unsaved` confirms quitting after the save failed.
+    fn quit(&mut self, unsaved: bool) -> bool {
+        if unsaved {
+            return true;
+        }
+        self.report_save(self.save_tasks());
+        if !self.dirty {
+            return true;
+        }
+        if let Some(Status::Error(message)) = &mut self.status {
+            message.push_str("; q again quits without saving");
+            self.quit_unsaved = true;
+        }
+        false
+    }
 
-            Row::new(vec![
-                Cell::from(item.title.clone()),
-                     Cell::from(item.description.clone()),
-                     Cell::from(item.target_date.format("%Y-%m-%d").to_string()),
-                     Cell::from(status).style(Style::default().fg(status_color)),
-            ])
-            .style(item.get_row_style())
-            .height(1)
+    fn report_save(&mut self, result: io::Result<()>) {
+        self.dirty = result.is_err();
+        if let Err(err) = &result
+            && err.get_ref().is_some_and(|inner| inner.is::<ExternalChange>())
+        {
+            self.save_conflict = true;
+            return;
+        }
+        self.status = Some(match result {
+            Ok(()) => Status::Info("Saved".to_string()),
+            Err(err) => Status::Error(format!(
+                "Failed to save: {} ({})",
+                err.kind(),
+                self.save_path.display()
+            )),
         });
+    }
 
-        let sort_indicator = match self.sort_mode {
-            SortMode::CreatedDate => " [Sorted by Date]",
-            SortMode::TargetDate => " [Sorted by Target]",
-            SortMode::Completion => " [Sorted by Status]",
-        };
+    fn update_scroll_state(&mut self) {
+        self.scroll_state = ScrollbarState::new(self.items.len());
+    }
 
-        let table = Table::new(
-            rows,
-            [
-                Constraint::Percentage(30),
-                               Constraint::Percentage(40),
-                               Constraint::Percentage(15),
-                               Constraint::Percentage(15),
-            ],
-        )
-        .header(header)
-        .block(Block::bordered().title(format!("Todo List{}", sort_indicator)))
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
-        .highlight_spacing(HighlightSpacing::Always);
+    fn set_sort_mode(&mut self, sort_mode: SortMode) {
+        self.sort_mode = sort_mode;
+        self.sort_items();
+        self.mark_dirty();
+    }
 
-        frame.render_stateful_widget(table, area, &mut self.state);
+    fn toggle_sort_direction(&mut self) {
+        self.sort_ascending = !self.sort_ascending;
+        self.sort_items();
+        self.mark_dirty();
     }
 
-    fn render_footer(&self, frame: &mut Frame, area: Rect) {
-        let info_text = Text::from_iter(INFO_TEXT.iter().map(|&s| Line::from(s)));
-        let footer = Paragraph::new(info_text)
-        .style(Style::default().fg(Color::Gray))
-        .block(Block::bordered().title("Controls"))
-        .alignment(Alignment::Center);
+    fn focus_column(&mut self, forward: bool) {
+        self.focused_column = if forward {
+            (self.focused_column + 1).min(COLUMN_TITLES.len() - 1)
+        } else {
+            self.focused_column.saturating_sub(1)
+        };
+    }
 
-        frame.render_widget(footer, area);
+    fn resize_focused_column(&mut self, grow: bool) {
+        match self.column_widths.resize(self.focused_column, grow) {
+            Some(widths) => {
+                self.column_widths = widths;
+                self.mark_dirty();
+            }
+            None => {
+                self.status = Some(Status::Info(format!(
+                    "Columns can't be narrower than {}%",
+                    MIN_COLUMN_WIDTH
+                )));
+            }
+        }
     }
 
-    fn render_form_popup(&self, frame: &mut Frame) {
-        let area = frame.area();
-        let popup_area = Rect {
-            x: area.width / 4,
-            y: area.height / 4,
-            width: area.width / 2,
-            height: area.height / 2,
-        };
+    fn sort_items(&mut self) {
+        let sort_mode = self.sort_mode;
+        let ascending = self.sort_ascending;
+        if sort_mode == SortMode::Manual {
+            // Tasks that aren't in the arrangement yet, like new ones, go to the bottom
+            let order = &self.manual_order;
+            self.items.sort_by_key(|item| {
+                order.iter().position(|&id| id == item.id).unwrap_or(usize::MAX)
+            });
+            self.manual_order = self.items.iter().map(|item| item.id).collect();
+            return;
+        }
+        self.items.sort_by(|a, b| {
+            let ordering = match sort_mode {
+                SortMode::CreatedDate => b.created_date.cmp(&a.created_date),
+                SortMode::TargetDate => a.target_datetime.cmp(&b.target_datetime),
+                SortMode::Completion => a.completed.cmp(&b.completed),
+                SortMode::Priority => b.priority.cmp(&a.priority),
+                SortMode::Manual => unreachable!(),
+            };
+            if ascending { ordering } else { ordering.reverse() }
+        });
+    }
 
-        frame.render_widget(Clear, popup_area);
+    /// Swaps the highlighted task with the visible one above or below it. Starts manual
+    /// sorting from the order on screen when another sort mode is active.
+    fn move_selected(&mut self, up: bool) {
+        let visible = self.visible_indices();
+        let Some(selected) = self.state.selected().filter(|&i| i < visible.len()) else {
+            return;
+        };
+        let target = if up { selected.checked_sub(1) } else { Some(selected + 1) };
+        let Some(target) = target.filter(|&i| i < visible.len()) else {
+            return;
+        };
+        self.push_undo(Some(self.items[visible[selected]].id));
+        if self.sort_mode != SortMode::Manual {
+            self.sort_mode = SortMode::Manual;
+            self.status = Some(Status::Info("Sorting manually; s/t/c/o sort again".to_string()));
+        }
+        self.items.swap(visible[selected], visible[target]);
+        self.manual_order = self.items.iter().map(|item| item.id).collect();
+        self.state.select(Some(target));
+        self.update_scroll_state();
+        self.mark_dirty();
+    }
 
-        let title = if self.mode == AppMode::AddTask {
-            "Add New Task"
-        } else {
-            "Edit Task"
+    fn matches_filter(&self, item: &TodoItem) -> bool {
+        if item.archived {
+            return false;
+        }
+        if self.hide_completed && item.completed {
+            return false;
+        }
+        if self.date_filter.is_some_and(|day| item.target_datetime.date() != day) {
+            return false;
+        }
+        let Some(query) = self.search_query.as_deref().filter(|q| !q.is_empty()) else {
+            return true;
         };
+        if let Some(tag) = query.strip_prefix('#') {
+            return tag.is_empty() || item.has_tag(tag);
+        }
+        let query = query.to_lowercase();
+        item.title.to_lowercase().contains(&query)
+            || item.description.to_lowercase().contains(&query)
+    }
 
-        let form_layout = Layout::vertical([
-            Constraint::Length(3),
-                                           Constraint::Length(3),
-                                           Constraint::Length(3),
-                                           Constraint::Min(2),
-        ]);
-        let form_chunks = form_layout.split(popup_area.inner(Margin {
-            vertical: 1,
-            horizontal: 1,
-        }));
+    /// Indices into `items` of the rows currently shown in the table, in display order
+    fn visible_indices(&self) -> Vec<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| self.matches_filter(item))
+            .map(|(i, _)| i)
+            .collect()
+    }
 
-        let popup_block = Block::bordered()
-        .title(title)
-        .style(Style::default().bg(Color::Black));
-        frame.render_widget(popup_block, popup_area);
+    /// Index into `items` of the highlighted row
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.state.selected()?;
+        self.visible_indices().get(selected).copied()
+    }
 
-        // Title field
-        let title_style = if self.form.field_index == 0 {
-            Style::default().fg(Color::Yellow)
+    fn clamp_selection(&mut self) {
+        let visible = self.visible_indices().len();
+        if visible == 0 {
+            self.state.select(None);
         } else {
-            Style::default()
-        };
-        let title_input = Paragraph::new(self.form.title.as_str())
-        .block(Block::bordered().title("Title").style(title_style))
-        .style(title_style);
-        frame.render_widget(title_input, form_chunks[0]);
+            let selected = self.state.selected().unwrap_or(0).min(visible - 1);
+            self.state.select(Some(selected));
+        }
+    }
 
-        // Description field
-        let desc_style = if self.form.field_index == 1 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
+    fn next_row(&mut self) {
+        let visible = self.visible_indices().len();
+        if visible == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => (i + 1) % visible,
+            None => 0,
         };
-        let desc_input = Paragraph::new(self.form.description.as_str())
-        .block(Block::bordered().title("Description").style(desc_style))
-        .style(desc_style);
-        frame.render_widget(desc_input, form_chunks[1]);
+        self.state.select(Some(i));
+    }
 
-        // Target date field
-        let date_style = if self.form.field_index == 2 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
+    /// Visible row of the next open task that is overdue or due today, after the selected
+    /// one and wrapping around
+    fn find_next_overdue(&self) -> Option<usize> {
+        let visible = self.visible_indices();
+        let start = self.state.selected().map_or(0, |i| i + 1);
+        (0..visible.len())
+            .map(|offset| (start + offset) % visible.len())
+            .find(|&row| self.items[visible[row]].is_due_by_today())
+    }
+
+    /// Like `find_next_overdue`, searching upwards from the selected row
+    fn find_prev_overdue(&self) -> Option<usize> {
+        let visible = self.visible_indices();
+        let start = self.state.selected().unwrap_or(0) + visible.len();
+        (1..=visible.len())
+            .map(|offset| (start - offset) % visible.len())
+            .find(|&row| self.items[visible[row]].is_due_by_today())
+    }
+
+    fn jump_to_overdue(&mut self, forward: bool) {
+        let row = if forward { self.find_next_overdue() } else { self.find_prev_overdue() };
+        match row {
+            Some(row) => self.state.select(Some(row)),
+            None => self.status = Some(Status::Info("No overdue tasks".to_string())),
+        }
+    }
+
+    fn first_row(&mut self) {
+        if !self.visible_indices().is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    fn last_row(&mut self) {
+        let visible = self.visible_indices().len();
+        if visible > 0 {
+            self.state.select(Some(visible - 1));
+        }
+    }
+
+    fn previous_row(&mut self) {
+        let visible = self.visible_indices().len();
+        if visible == 0 {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    visible - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
         };
-        let date_input = Paragraph::new(self.form.target_date.as_str())
-        .block(Block::bordered().title("Target Date (YYYY-MM-DD)").style(date_style))
-        .style(date_style);
-        frame.render_widget(date_input, form_chunks[2]);
+        self.state.select(Some(i));
+    }
 
-        // Instructions
-        let instructions = Paragraph::new("Tab/Shift+Tab: Navigate | Enter: Save | Esc: Cancel")
-        .style(Style::default().fg(Color::Gray))
-        .alignment(Alignment::Center);
-        frame.render_widget(instructions, form_chunks[3]);
+    fn selected_id(&self) -> Option<usize> {
+        self.selected_index().map(|i| self.items[i].id)
     }
-}
 
-fn main() -> Result<()> {
-    color_eyre::install()?;
-    let terminal = ratatui::init();
-    let app_result = App::new().run(terminal);
-    ratatui::restore();
-    app_result
-}
+    /// Moves the cursor to the task with `id`, or keeps it in range if that task isn't shown
+    fn select_task(&mut self, id: usize) {
+        match self.visible_indices().iter().position(|&i| self.items[i].id == id) {
+            Some(row) => self.state.select(Some(row)),
+            None => self.clamp_selection(),
+        }
+    }
+
+    fn push_undo(&mut self, task_id: Option<usize>) {
+        let record = self.snapshot(task_id);
+        self.undo_stack.push(record);
+    }
 
+    fn snapshot(&self, task_id: Option<usize>) -> UndoRecord {
+        UndoRecord {
+            items: self.items.clone(),
+            trash: self.trash.clone(),
+            next_id: self.next_id,
+            task_id,
+        }
+    }
+
+    fn undo(&mut self) {
+        let current = self.snapshot(self.selected_id());
+        match self.undo_stack.undo(current) {
+            Some(record) => self.apply_undo_record(record),
+            None => self.status = Some(Status::Info("Nothing to undo".to_string())),
+        }
+    }
+
+    fn redo(&mut self) {
+        let current = self.snapshot(self.selected_id());
+        match self.undo_stack.redo(current) {
+            Some(record) => self.apply_undo_record(record),
+            None => self.status = Some(Status::Info("Nothing to redo".to_string())),
+        }
+    }
+
+    fn apply_undo_record(&mut self, record: UndoRecord) {
+        self.items = record.items;
+        self.trash = record.trash;
+        self.next_id = record.next_id;
+        // Undoing a move brings back the arrangement from before it
+        if self.sort_mode == SortMode::Manual {
+            self.manual_order = self.items.iter().map(|item| item.id).collect();
+        }
+        self.clamp_trash_selection();
+        self.selected_ids
+            .retain(|id| self.items.iter().any(|item| item.id == *id));
+        match record.task_id {
+            Some(id) => self.select_task(id),
+            None => self.clamp_selection(),
+        }
+        self.update_scroll_state();
+        self.mark_dirty();
+    }
+
+    fn toggle_marked(&mut self) {
+        if let Some(selected) = self.selected_index() {
+            let id = self.items[selected].id;
+            if !self.selected_ids.remove(&id) {
+                self.selected_ids.insert(id);
+            }
+        }
+    }
+
+    fn toggle_completed(&mut self) {
+        if !self.selected_ids.is_empty() {
+            self.push_undo(self.selected_id());
+            let indices: Vec<usize> = (0..self.items.len())
+                .filter(|&i| self.selected_ids.contains(&self.items[i].id))
+                .collect();
+            for i in indices {
+                self.toggle_item(i);
+            }
+            self.selected_ids.clear();
+            self.update_scroll_state();
+            self.mark_dirty();
+            return;
+        }
+        if let Some(selected) = self.selected_index() {
+            self.push_undo(Some(self.items[selected].id));
+            self.toggle_item(selected);
+            self.update_scroll_state();
+            self.mark_dirty();
+        }
+    }
+
+    /// Flips completion of `items[index]`. Completing a recurring task appends its next
+    /// occurrence; the completed one is kept as history.
+    fn toggle_item(&mut self, index: usize) {
+        let item = &mut self.items[index];
+        item.completed = !item.completed;
+        item.touch();
+        item.completed_date = item.completed.then(|| Local::now().date_naive());
+        if !item.completed {
+            return;
+        }
+        let spawned = item.next_occurrence_id;
+        if spawned.is_some_and(|id| self.items.iter().any(|other| other.id == id)) {
+            return;
+        }
+        if let Some(next) = self.items[index].next_occurrence(self.next_id) {
+            self.items[index].next_occurrence_id = Some(next.id);
+            self.items.push(next);
+            self.next_id += 1;
+        }
+    }
+
+    fn cycle_priority(&mut self) {
+        if let Some(selected) = self.selected_index() {
+            self.push_undo(Some(self.items[selected].id));
+            let item = &mut self.items[selected];
+            item.priority = item.priority.next();
+            item.touch();
+            self.mark_dirty();
+        }
+    }
+
+    /// Moves the task in `pending_delete` to the trash, or every marked task without one
+    fn delete_pending(&mut self) {
+        let deleted_date = Local::now().date_naive();
+        if let Some(id) = self.pending_delete.take() {
+            let Some(index) = self.items.iter().position(|item| item.id == id) else {
+                self.status = Some(Status::Info("That task is already gone".to_string()));
+                return;
+            };
+            self.push_undo(Some(id));
+            let task = self.items.remove(index);
+            self.trash.push(TrashedItem { deleted_date, task });
+            self.clamp_selection();
+            self.update_scroll_state();
+            self.mark_dirty();
+            return;
+        }
+        if !self.selected_ids.is_empty() {
+            self.push_undo(self.selected_id());
+            let (deleted, kept): (Vec<TodoItem>, Vec<TodoItem>) = std::mem::take(&mut self.items)
+                .into_iter()
+                .partition(|item| self.selected_ids.contains(&item.id));
+            self.items = kept;
+            self.trash
+                .extend(deleted.into_iter().map(|task| TrashedItem { deleted_date, task }));
+            self.selected_ids.clear();
+            self.clamp_selection();
+            self.update_scroll_state();
+            self.mark_dirty();
+        }
+    }
+
+    /// Drops trash entries deleted more than `TRASH_RETENTION_DAYS` ago
+    fn purge_old_trash(&mut self) {
+        let today = Local::now().date_naive();
+        let before = self.trash.len();
+        self.trash
+            .retain(|entry| (today - entry.deleted_date).num_days() <= TRASH_RETENTION_DAYS);
+        if self.trash.len() != before {
+            self.clamp_trash_selection();
+            self.mark_dirty();
+        }
+    }
+
+    fn open_trash_view(&mut self) {
+        self.trash_state
+            .select(if self.trash.is_empty() { None } else { Some(self.trash.len() - 1) });
+        self.mode = AppMode::TrashView;
+    }
+
+    fn clamp_trash_selection(&mut self) {
+        self.trash_state.select(match self.trash.len() {
+            0 => None,
+            count => Some(self.trash_state.selected().unwrap_or(0).min(count - 1)),
+        });
+    }
+
+    /// Puts the highlighted trash entry back in the list. It gets a new id if its old one
+    /// has been taken since.
+    fn restore_from_trash(&mut self) {
+        if self.read_only {
+            self.report_read_only();
+            return;
+        }
+        let Some(index) = self.trash_state.selected().filter(|&i| i < self.trash.len()) else {
+            return;
+        };
+        self.push_undo(self.selected_id());
+        let mut task = self.trash.remove(index).task;
+        if self.items.iter().any(|item| item.id == task.id) {
+            task.id = self.next_id;
+        }
+        self.next_id = self.next_id.max(task.id + 1);
+        self.status = Some(Status::Info(format!("Restored '{}' from the trash", task.title)));
+        let id = task.id;
+        self.items.push(task);
+        self.sort_items();
+        self.select_task(id);
+        self.clamp_trash_selection();
+        self.update_scroll_state();
+        self.mark_dirty();
+    }
+
+    /// Deletes the highlighted trash entry for good (still undoable until quitting)
+    fn purge_from_trash(&mut self) {
+        if self.read_only {
+            self.report_read_only();
+            return;
+        }
+        let Some(index) = self.trash_state.selected().filter(|&i| i < self.trash.len()) else {
+            return;
+        };
+        self.push_undo(self.selected_id());
+        let entry = self.trash.remove(index);
+        self.status = Some(Status::Info(format!("Purged '{}'", entry.task.title)));
+        self.clamp_trash_selection();
+        self.mark_dirty();
+    }
+
+    fn start_delete(&mut self) {
+        self.pending_delete = None;
+        if self.selected_ids.is_empty() {
+            self.pending_delete = self.selected_id();
+            if self.pending_delete.is_none() {
+                return;
+            }
+        }
+        self.mode = AppMode::ConfirmDelete;
+    }
+
+    /// Writes the visible tasks to a CSV file next to the save file, e.g. `todos.csv`
+    fn export_csv(&mut self) {
+        let visible = self.visible_indices();
+        let csv = export::to_csv(visible.iter().map(|&i| &self.items[i]));
+        let path = self.save_path.with_extension("csv");
+        self.status = Some(match fs::write(&path, csv) {
+            Ok(()) => Status::Info(format!("Exported {} tasks to {}", visible.len(), path.display())),
+            Err(err) => Status::Error(format!("Failed to export: {} ({})", err.kind(), path.display())),
+        });
+    }
+
+    fn export_markdown(&mut self) {
+        let visible = self.visible_indices();
+        let markdown = export::to_markdown(visible.iter().map(|&i| &self.items[i]));
+        let path = self.save_path.with_extension("md");
+        self.status = Some(match fs::write(&path, markdown) {
+            Ok(()) => Status::Info(format!("Exported {} tasks to {}", visible.len(), path.display())),
+            Err(err) => Status::Error(format!("Failed to export: {} ({})", err.kind(), path.display())),
+        });
+    }
+
+    /// Writes the visible tasks as calendar to-dos next to the save file, e.g. `todos.ics`
+    fn export_ical(&mut self) {
+        let visible = self.visible_indices();
+        let ical = export::to_ical(visible.iter().map(|&i| &self.items[i]), &self.save_path);
+        let path = self.save_path.with_extension("ics");
+        self.status = Some(match fs::write(&path, ical) {
+            Ok(()) => Status::Info(format!("Exported {} tasks to {}", visible.len(), path.display())),
+            Err(err) => Status::Error(format!("Failed to export: {} ({})", err.kind(), path.display())),
+        });
+    }
+
+    /// Command-line export of the whole list (not just what a filter would show), in its
+    /// saved sort order
+    fn export_to(&self, format: ExportFormat, output: Option<&Path>, only: Option<TaskStatus>) -> Result<()> {
+        let items: Vec<&TodoItem> = self
+            .items
+            .iter()
+            .filter(|item| match only {
+                Some(TaskStatus::Pending) => !item.completed,
+                Some(TaskStatus::Completed) => item.completed,
+                None => true,
+            })
+            .collect();
+        let content = match format {
+            ExportFormat::Csv => export::to_csv(items.iter().copied()),
+            ExportFormat::Markdown => export::to_markdown(items.iter().copied()),
+            ExportFormat::TodoTxt => export::to_todo_txt(items.iter().copied()),
+            ExportFormat::Ical => export::to_ical(items.iter().copied(), &self.save_path),
+            ExportFormat::Org => export::to_org(items.iter().copied()),
+        };
+        match output {
+            Some(path) => {
+                fs::write(path, content).wrap_err_with(|| format!("failed to write {}", path.display()))?;
+                println!("Exported {} tasks to {}", items.len(), path.display());
+            }
+            None => print!("{}", content),
+        }
+        Ok(())
+    }
+
+    fn start_import(&mut self) {
+        self.mode = AppMode::Import;
+        self.import_path.clear();
+    }
+
+    /// Reads the file at `import_path` and asks whether to add the tasks found in it
+    fn preview_import(&mut self) {
+        let path = self.import_path.trim().to_string();
+        match import::read_file(Path::new(&path), None) {
+            Ok(result) if result.items.is_empty() => {
+                self.mode = AppMode::Normal;
+                self.notice = Some(Notice {
+                    title: "Import",
+                    message: format!("Found {}", import_summary(&result, Path::new(&path))),
+                });
+            }
+            Ok(result) => {
+                self.mode = AppMode::ConfirmImport;
+                self.import_preview = Some(result);
+            }
+            Err(err) => {
+                self.mode = AppMode::Normal;
+                self.status = Some(Status::Error(format!("Failed to import: {} ({})", err, path)));
+            }
+        }
+    }
+
+    /// Appends the previewed tasks and reports what was skipped
+    fn confirm_import(&mut self) {
+        self.mode = AppMode::Normal;
+        if let Some(result) = self.import_preview.take() {
+            let path = PathBuf::from(self.import_path.trim());
+            self.append_imported(&result);
+            self.notice = Some(Notice {
+                title: "Import",
+                message: format!("Imported {}", import_summary(&result, &path)),
+            });
+        }
+    }
+
+    /// Appends imported tasks to the list with fresh ids
+    fn append_imported(&mut self, result: &import::ImportResult) {
+        if result.items.is_empty() {
+            return;
+        }
+        self.push_undo(self.selected_id());
+        for mut item in result.items.iter().cloned() {
+            item.id = self.next_id;
+            self.next_id += 1;
+            self.items.push(item);
+        }
+        self.sort_items();
+        self.clamp_selection();
+        self.update_scroll_state();
+        self.mark_dirty();
+    }
+
+    /// Command-line import: lists what the file holds and appends it once confirmed
+    fn import_file(&mut self, path: &Path, format: Option<import::ImportFormat>, yes: bool) -> Result<()> {
+        if self.read_only {
+            return Err(eyre!("{} is open in another rtodo", self.save_path.display()));
+        }
+        let result = import::read_file(path, format)
+            .map_err(|err| eyre!("failed to import {}: {}", path.display(), err))?;
+        println!("Importing into {}: {}", self.save_path.display(), import_summary(&result, path));
+        if result.items.is_empty() {
+            println!("Nothing to import.");
+            return Ok(());
+        }
+        if !yes {
+            print!("Add these {} tasks? [y/N] ", result.items.len());
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                println!("Left {} untouched.", self.save_path.display());
+                return Ok(());
+            }
+        }
+
+        self.append_imported(&result);
+        self.flush();
+        if self.dirty {
+            return Err(eyre!("failed to save {}", self.save_path.display()));
+        }
+        println!("Imported {} tasks.", result.items.len());
+        Ok(())
+    }
+
+    /// Raw entries of the archive file, empty if there is none yet
+    fn read_archive(&self) -> io::Result<Vec<serde_json::Value>> {
+        let path = storage::archive_path(&self.save_path);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let content = self.decode(content).map_err(io::Error::other)?;
+        let state = storage::parse_state(&content, StorageFormat::from_path(&path))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        Ok(state.items)
+    }
+
+    fn write_archive(&self, items: Vec<serde_json::Value>) -> io::Result<()> {
+        let path = storage::archive_path(&self.save_path);
+        let state = AppState {
+            version: storage::CURRENT_VERSION,
+            settings: Settings {
+                compact_json: self.compact_json,
+                ..Settings::default()
+            },
+            next_id: 0,
+            items,
+            trash: Vec::new(),
+        };
+        let content = storage::serialize_state(&state, StorageFormat::from_path(&path))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        storage::write_atomically(&path, &self.encode(content)?)
+    }
+
+    fn start_archive(&mut self) {
+        if self.items.iter().any(|item| item.completed) {
+            self.mode = AppMode::ConfirmArchive;
+        } else {
+            self.status = Some(Status::Info("No completed tasks to archive".to_string()));
+        }
+    }
+
+    /// Moves every completed task to the end of the archive file. The archive isn't part
+    /// of the undo history, so earlier snapshots are dropped rather than left able to bring
+    /// archived tasks back as duplicates.
+    fn archive_completed(&mut self) {
+        let (completed, pending): (Vec<TodoItem>, Vec<TodoItem>) =
+            std::mem::take(&mut self.items).into_iter().partition(|item| item.completed);
+        self.items = pending;
+        let result = self.read_archive().and_then(|mut archive| {
+            let previous = archive.clone();
+            for item in &completed {
+                archive.push(serde_json::to_value(item)?);
+            }
+            self.write_archive(archive).map(|()| previous)
+        });
+        let previous = match result {
+            Ok(previous) => previous,
+            Err(err) => {
+                self.items.extend(completed);
+                self.sort_items();
+                self.status = Some(Status::Error(format!(
+                    "Failed to archive: {} ({})",
+                    err,
+                    storage::archive_path(&self.save_path).display()
+                )));
+                return;
+            }
+        };
+        // Saved right away rather than on the next autosave, so a crash can't leave the
+        // tasks in both files
+        if let Err(err) = self.save_tasks() {
+            // If even this fails the tasks are in both files, but none are lost
+            let _ = self.write_archive(previous);
+            self.items.extend(completed);
+            self.sort_items();
+            self.report_save(Err(err));
+            return;
+        }
+
+        self.selected_ids.retain(|id| self.items.iter().any(|item| item.id == *id));
+        self.undo_stack = UndoStack::default();
+        self.clamp_selection();
+        self.update_scroll_state();
+        self.record_changes();
+        self.dirty = false;
+        self.last_save = Instant::now();
+        self.status = Some(Status::Info(format!(
+            "Archived {} tasks to {}",
+            completed.len(),
+            storage::archive_path(&self.save_path).display()
+        )));
+    }
+
+    /// Archives the highlighted task if it is done. Unlike Shift+A it stays in the save
+    /// file, hidden from the table, and can be undone.
+    fn archive_selected(&mut self) {
+        let Some(selected) = self.selected_index() else {
+            return;
+        };
+        if !self.items[selected].completed {
+            self.status = Some(Status::Info("Only completed tasks can be archived".to_string()));
+            return;
+        }
+        let id = self.items[selected].id;
+        self.push_undo(Some(id));
+        let item = &mut self.items[selected];
+        item.archived = true;
+        item.touch();
+        self.status = Some(Status::Info(format!("Archived '{}'", item.title)));
+        self.selected_ids.remove(&id);
+        self.clamp_selection();
+        self.update_scroll_state();
+        self.mark_dirty();
+    }
+
+    /// Indices into `items` of the tasks archived with `f`, listed in the archive view
+    /// before the tasks in the archive file
+    fn archived_indices(&self) -> Vec<usize> {
+        (0..self.items.len()).filter(|&i| self.items[i].archived).collect()
+    }
+
+    /// Rows in the archive view: tasks archived in the list, then those in the archive file
+    fn archive_len(&self) -> usize {
+        self.archived_indices().len() + self.archive_items.len()
+    }
+
+    fn open_archive_view(&mut self) {
+        let archive = match self.read_archive() {
+            Ok(archive) => archive,
+            Err(err) => {
+                self.status = Some(Status::Error(format!(
+                    "Failed to read archive: {} ({})",
+                    err,
+                    storage::archive_path(&self.save_path).display()
+                )));
+                return;
+            }
+        };
+        self.archive_items = archive
+            .into_iter()
+            .filter_map(|value| serde_json::from_value(value).ok())
+            .collect();
+        self.archive_state.select(if self.archive_len() == 0 { None } else { Some(0) });
+        self.mode = AppMode::ArchiveView;
+    }
+
+    /// Moves the highlighted archived task back into the list, keeping its id
+    fn unarchive_selected(&mut self) {
+        if self.read_only {
+            self.report_read_only();
+            return;
+        }
+        let Some(selected) = self.archive_state.selected() else {
+            return;
+        };
+        let archived = self.archived_indices();
+        if let Some(&index) = archived.get(selected) {
+            self.push_undo(Some(self.items[index].id));
+            let item = &mut self.items[index];
+            item.archived = false;
+            item.touch();
+            self.status = Some(Status::Info(format!("Restored '{}' from the archive", item.title)));
+            self.update_scroll_state();
+            self.mark_dirty();
+            self.clamp_archive_selection(selected);
+            return;
+        }
+        let Some(item) = self.archive_items.get(selected - archived.len()) else {
+            return;
+        };
+        let mut item = item.clone();
+        let id = item.id;
+        // It may have been archived with `f` before Shift+A moved it to the file
+        item.archived = false;
+        // The list is saved before the task leaves the archive, so a crash in between
+        // can't lose it
+        self.items.push(item);
+        if let Err(err) = self.save_tasks() {
+            self.items.pop();
+            self.report_save(Err(err));
+            return;
+        }
+        // Rewritten from the file rather than `archive_items`, so entries the view couldn't
+        // decode are kept
+        let result = self.read_archive().and_then(|mut archive| {
+            archive.retain(|value| value.get("id").and_then(|id| id.as_u64()) != Some(id as u64));
+            self.write_archive(archive)
+        });
+        if let Err(err) = result {
+            // Take it back out of the list rather than keep it in both files
+            self.items.pop();
+            let _ = self.save_tasks();
+            self.status = Some(Status::Error(format!(
+                "Failed to update archive: {} ({})",
+                err,
+                storage::archive_path(&self.save_path).display()
+            )));
+            return;
+        }
+
+        let item = self.archive_items.remove(selected - archived.len());
+        self.status = Some(Status::Info(format!("Restored '{}' from the archive", item.title)));
+        self.next_id = self.next_id.max(item.id + 1);
+        self.undo_stack = UndoStack::default();
+        self.sort_items();
+        self.update_scroll_state();
+        self.record_changes();
+        self.dirty = false;
+        self.last_save = Instant::now();
+        self.clamp_archive_selection(selected);
+    }
+
+    /// Keeps the archive view's highlight on the row that took the place of a restored one
+    fn clamp_archive_selection(&mut self, selected: usize) {
+        self.archive_state.select(match self.archive_len() {
+            0 => None,
+            count => Some(selected.min(count - 1)),
+        });
+    }
+
+    fn start_search(&mut self) {
+        self.mode = AppMode::Search;
+        self.search_query = Some(String::new());
+        self.state.select(Some(0));
+        self.clamp_selection();
+    }
+
+    fn lock_search(&mut self) {
+        self.mode = AppMode::Normal;
+        if self.search_query.as_deref() == Some("") {
+            self.search_query = None;
+        }
+    }
+
+    fn clear_search(&mut self) {
+        self.mode = AppMode::Normal;
+        self.search_query = None;
+        self.clamp_selection();
+    }
+
+    /// Steps the tag filter through every tag in use, then back to showing all tasks
+    fn cycle_tag_filter(&mut self) {
+        let mut tags: Vec<&String> = self
+            .items
+            .iter()
+            .filter(|item| !item.archived)
+            .flat_map(|item| &item.tags)
+            .collect();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        if tags.is_empty() {
+            self.status = Some(Status::Info("No tags to filter by".to_string()));
+            return;
+        }
+
+        let current = self.search_query.as_deref().and_then(|query| query.strip_prefix('#'));
+        let next = match current.and_then(|tag| tags.iter().position(|t| t.eq_ignore_ascii_case(tag))) {
+            Some(pos) => tags.get(pos + 1),
+            None => tags.first(),
+        };
+        self.search_query = next.map(|tag| format!("#{}", tag));
+        self.state.select(Some(0));
+        self.clamp_selection();
+    }
+
+    /// Opens the calendar on the selected task's due date, or today
+    fn start_calendar(&mut self) {
+        self.calendar_day = self
+            .date_filter
+            .or_else(|| self.selected_index().map(|i| self.items[i].target_datetime.date()))
+            .unwrap_or_else(|| Local::now().date_naive());
+        self.mode = AppMode::CalendarView;
+    }
+
+    fn move_calendar_days(&mut self, days: i64) {
+        let day = if days < 0 {
+            self.calendar_day.checked_sub_days(Days::new(days.unsigned_abs()))
+        } else {
+            self.calendar_day.checked_add_days(Days::new(days as u64))
+        };
+        if let Some(day) = day {
+            self.calendar_day = day;
+        }
+    }
+
+    /// `[` and `]` step a month back or forward, clamping to the end of shorter months
+    fn move_calendar_month(&mut self, forward: bool) {
+        let day = if forward {
+            self.calendar_day.checked_add_months(Months::new(1))
+        } else {
+            self.calendar_day.checked_sub_months(Months::new(1))
+        };
+        if let Some(day) = day {
+            self.calendar_day = day;
+        }
+    }
+
+    /// Opens the form's date picker on the date already typed, or today
+    fn open_date_picker(&mut self) {
+        self.calendar_day = date_utils::parse_datetime_input(&self.form.target_date)
+            .map_or_else(|| Local::now().date_naive(), |deadline| deadline.date());
+        self.date_picker_open = true;
+    }
+
+    /// Puts the picked day into the date field, keeping a time that was typed there
+    fn pick_date(&mut self) {
+        let time = date_utils::parse_datetime_input(&self.form.target_date)
+            .map_or(date_utils::END_OF_DAY, |deadline| deadline.time());
+        self.form.target_date = date_utils::format_deadline(self.calendar_day.and_time(time));
+        self.form.move_cursor_end();
+        self.date_picker_open = false;
+    }
+
+    fn filter_to_calendar_day(&mut self) {
+        self.mode = AppMode::Normal;
+        self.date_filter = Some(self.calendar_day);
+        self.state.select(Some(0));
+        self.clamp_selection();
+    }
+
+    /// Shows or hides completed tasks, staying on the selected task when it remains visible
+    fn toggle_hide_completed(&mut self) {
+        let selected = self.selected_id();
+        self.hide_completed = !self.hide_completed;
+        match selected {
+            Some(id) => self.select_task(id),
+            None => self.clamp_selection(),
+        }
+        self.update_scroll_state();
+    }
+
+    /// Shows or hides the subtasks of the selected task
+    fn toggle_expanded(&mut self) {
+        let Some(selected) = self.selected_index() else {
+            return;
+        };
+        let item = &self.items[selected];
+        if item.subtasks.is_empty() {
+            self.status = Some(Status::Info("No subtasks yet, + adds one".to_string()));
+        } else if !self.expanded.remove(&item.id) {
+            self.expanded.insert(item.id);
+        }
+    }
+
+    fn start_add_subtask(&mut self) {
+        if self.selected_index().is_some() {
+            self.mode = AppMode::AddSubtask;
+            self.subtask_input.clear();
+        }
+    }
+
+    /// Adds `subtask_input` to the selected task, expanding it to show the new step
+    fn add_subtask(&mut self) {
+        let title = self.subtask_input.trim().to_string();
+        let Some(selected) = self.selected_index() else {
+            self.mode = AppMode::Normal;
+            return;
+        };
+        if title.is_empty() {
+            return;
+        }
+        self.mode = AppMode::Normal;
+        self.push_undo(Some(self.items[selected].id));
+        let item = &mut self.items[selected];
+        let id = item.subtasks.iter().map(|subtask| subtask.id + 1).max().unwrap_or(1);
+        item.subtasks.push(SubTask { id, title, completed: false });
+        item.touch();
+        self.expanded.insert(item.id);
+        self.mark_dirty();
+    }
+
+    /// Ticks or unticks subtask `number` (counting from 1) of the selected task, if expanded
+    fn toggle_subtask(&mut self, number: usize) {
+        let Some(selected) = self.selected_index() else {
+            return;
+        };
+        let id = self.items[selected].id;
+        if !self.expanded.contains(&id) || number > self.items[selected].subtasks.len() {
+            return;
+        }
+        self.push_undo(Some(id));
+        let item = &mut self.items[selected];
+        let subtask = &mut item.subtasks[number - 1];
+        subtask.completed = !subtask.completed;
+        item.touch();
+        self.mark_dirty();
+    }
+
+    /// Drops the ticked-off subtasks of the selected task, if expanded
+    fn clear_done_subtasks(&mut self) {
+        let Some(selected) = self.selected_index() else {
+            return;
+        };
+        let id = self.items[selected].id;
+        if !self.expanded.contains(&id) || self.items[selected].subtask_progress().0 == 0 {
+            return;
+        }
+        self.push_undo(Some(id));
+        let item = &mut self.items[selected];
+        item.subtasks.retain(|subtask| !subtask.completed);
+        item.touch();
+        if item.subtasks.is_empty() {
+            self.expanded.remove(&id);
+        }
+        self.mark_dirty();
+    }
+
+    /// Lines a task takes up in the table: one, plus one per subtask while expanded
+    fn row_height(&self, item: &TodoItem) -> u16 {
+        if self.expanded.contains(&item.id) {
+            1 + item.subtasks.len() as u16
+        } else {
+            1
+        }
+    }
+
+    fn clear_date_filter(&mut self) {
+        self.date_filter = None;
+        self.clamp_selection();
+    }
+
+    /// Fills `projects` and points `project_selected` at the open one. False (with the
+    /// error in the footer) if the project directory can't be read.
+    fn refresh_projects(&mut self) -> bool {
+        self.projects = match storage::list_projects(&self.project_dir) {
+            Ok(projects) => projects,
+            Err(err) => {
+                self.status = Some(Status::Error(format!(
+                    "Failed to list projects: {} ({})",
+                    err.kind(),
+                    self.project_dir.display()
+                )));
+                return false;
+            }
+        };
+        // A list that was never saved has no file yet but is still open
+        if !self.projects.contains(&self.save_path) {
+            self.projects.push(self.save_path.clone());
+            self.projects.sort();
+        }
+        self.project_selected = self
+            .projects
+            .iter()
+            .position(|path| *path == self.save_path)
+            .unwrap_or(0);
+        true
+    }
+
+    fn start_project_switcher(&mut self) {
+        if self.refresh_projects() {
+            self.mode = AppMode::ProjectSwitcher;
+        }
+    }
+
+    /// `[` and `]` open the previous or next project without going through the switcher
+    fn cycle_project(&mut self, forward: bool) {
+        if !self.refresh_projects() {
+            return;
+        }
+        if self.projects.len() < 2 {
+            self.status = Some(Status::Info("No other projects; Shift+P to create one".to_string()));
+            return;
+        }
+        let count = self.projects.len();
+        let next = if forward {
+            (self.project_selected + 1) % count
+        } else {
+            (self.project_selected + count - 1) % count
+        };
+        let path = self.projects[next].clone();
+        self.open_project(path);
+    }
+
+    fn start_new_project(&mut self) {
+        self.project_name.clear();
+        self.mode = AppMode::NewProject;
+    }
+
+    fn create_project(&mut self) {
+        let name = self.project_name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            self.status = Some(Status::Error("Invalid project name".to_string()));
+            return;
+        }
+        let file_name = if name.ends_with(".toml") || name.ends_with(".json") {
+            name.to_string()
+        } else {
+            format!("{}.json", name)
+        };
+        let path = self.project_dir.join(file_name);
+        if path.exists() {
+            self.status = Some(Status::Error(format!("Project {} already exists", name)));
+            return;
+        }
+        self.open_project(path);
+        // Write the empty list right away so the project shows up in the switcher
+        if !self.read_only {
+            self.report_save(self.save_tasks());
+        }
+    }
+
+    /// Saves the current list and replaces it with the project stored at `path`
+    fn open_project(&mut self, path: PathBuf) {
+        self.mode = AppMode::Normal;
+        if path == self.save_path {
+            return;
+        }
+        self.report_save(self.save_tasks());
+        if self.dirty {
+            // The conflict popup or the save error is showing; don't leave changes behind
+            return;
+        }
+
+        match lock::acquire(&path) {
+            Ok(lock::LockAttempt::Acquired(file_lock)) => self.switch_project(path, Some(file_lock)),
+            Ok(lock::LockAttempt::Held { pid, running }) => {
+                // Ask, as at startup, whether to open it read-only or take the lock over
+                self.locked_project = Some(LockedProject { path, pid, running });
+                self.mode = AppMode::ConfirmLock;
+            }
+            Err(err) => self.report_lock_error(&err, &path),
+        }
+    }
+
+    /// Answers the lock prompt: `t` takes the lock over, `r` opens the project read-only,
+    /// `c`, `q` or Esc stay in the current project
+    fn resolve_lock(&mut self, key: KeyCode) {
+        let Some(locked) = self.locked_project.take() else {
+            self.mode = AppMode::Normal;
+            return;
+        };
+        match key {
+            KeyCode::Char('t') | KeyCode::Char('T') => self.take_over_lock(locked.path),
+            // Taking over is the default once the owner is gone, as at startup
+            KeyCode::Enter if !locked.running => self.take_over_lock(locked.path),
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                self.mode = AppMode::Normal;
+                self.switch_project(locked.path, None);
+            }
+            KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+            }
+            _ => self.locked_project = Some(locked),
+        }
+    }
+
+    fn take_over_lock(&mut self, path: PathBuf) {
+        self.mode = AppMode::Normal;
+        match lock::reclaim(&path) {
+            Ok(file_lock) => self.switch_project(path, Some(file_lock)),
+            Err(err) => self.report_lock_error(&err, &path),
+        }
+    }
+
+    fn report_lock_error(&mut self, err: &io::Error, path: &Path) {
+        self.status = Some(Status::Error(format!(
+            "Failed to lock: {} ({})",
+            err.kind(),
+            path.display()
+        )));
+    }
+
+    /// Replaces this app with one for the project at `path`; without a `file_lock` it is
+    /// opened read-only
+    fn switch_project(&mut self, path: PathBuf, file_lock: Option<lock::FileLock>) {
+        // Other projects keep their own encryption; new ones follow the current project
+        let encrypt_on_save = match fs::read_to_string(&path) {
+            Ok(content) => crypto::is_encrypted(&content),
+            Err(_) => self.encrypt_on_save,
+        };
+        match App::new(
+            path,
+            self.backup_count,
+            file_lock,
+            self.cipher.clone(),
+            encrypt_on_save,
+            self.config,
+        ) {
+            Ok(app) => {
+                // Dropping the old app releases its lock
+                *self = app;
+                self.status = Some(if self.read_only {
+                    Status::Error(format!(
+                        "Opened {} read-only: another rtodo has it open",
+                        self.current_project
+                    ))
+                } else {
+                    Status::Info(format!("Opened {}", self.current_project))
+                });
+            }
+            Err(err) => {
+                self.status = Some(Status::Error(format!("Failed to open project: {}", err)));
+            }
+        }
+    }
+
+    fn start_add_task(&mut self) {
+        self.mode = AppMode::AddTask;
+        self.form.clear();
+        self.edit_id = None;
+    }
+
+    fn start_edit_task(&mut self) {
+        if let Some(selected) = self.selected_index()
+            && let Some(item) = self.items.get(selected)
+        {
+            self.mode = AppMode::EditTask;
+            self.edit_id = Some(item.id);
+            self.form.title = item.title.clone();
+            self.form.description = item.description.clone();
+            self.form.target_date = date_utils::format_deadline(item.target_datetime);
+            self.form.priority = item.priority;
+            self.form.tags = item.tags.join(", ");
+            self.form.recurrence = item.recurrence;
+            self.form.notes = item.notes.clone();
+            self.form.field_index = 0;
+            self.form.move_cursors_to_end();
+        }
+    }
+
+    fn start_notes_editor(&mut self) {
+        if let Some(selected) = self.selected_index() {
+            self.edit_id = Some(self.items[selected].id);
+            self.notes_input = self.items[selected].notes.clone();
+            self.mode = AppMode::NotesEditor;
+        }
+    }
+
+    fn save_notes(&mut self) {
+        self.mode = AppMode::Normal;
+        if let Some(edit_id) = self.edit_id.take()
+            && let Some(index) = self.items.iter().position(|item| item.id == edit_id)
+            && self.items[index].notes != self.notes_input
+        {
+            self.push_undo(Some(edit_id));
+            self.items[index].notes = std::mem::take(&mut self.notes_input);
+            self.items[index].touch();
+            self.mark_dirty();
+        }
+    }
+
+    /// Index of an open task with the same title (ignoring case) due on the same day
+    fn find_duplicate(&self, title: &str, date: NaiveDate) -> Option<usize> {
+        let title = title.trim().to_lowercase();
+        self.items.iter().position(|item| {
+            !item.completed
+                && item.target_datetime.date() == date
+                && item.title.trim().to_lowercase() == title
+        })
+    }
+
+    /// Adds or updates the task from the form. A new task that looks like an open one is
+    /// only added once `allow_duplicate` confirms it.
+    fn submit_form(&mut self, allow_duplicate: bool) {
+        if self.form.title.trim().is_empty() {
+            self.form.error = Some("Title cannot be empty".to_string());
+            self.form.field_index = 0;
+            return;
+        }
+        let Some(target_datetime) = date_utils::parse_datetime_input(&self.form.target_date) else {
+            self.form.error = Some("Invalid date, use YYYY-MM-DD [HH:MM], today, tomorrow or +3d/+2w/+1m".to_string());
+            self.form.field_index = 2;
+            return;
+        };
+        if self.mode == AppMode::AddTask
+            && !allow_duplicate
+            && let Some(existing) = self.find_duplicate(&self.form.title, target_datetime.date())
+        {
+            self.form.error = Some(format!(
+                "Similar task exists: '{}'. Submit again to add it anyway",
+                self.items[existing].title
+            ));
+            self.form.duplicate_warned = true;
+            return;
+        }
+
+        match self.mode {
+            AppMode::AddTask => {
+                self.push_undo(self.selected_id());
+                let mut item = TodoItem::new(
+                    self.next_id,
+                    self.form.title.clone(),
+                    self.form.description.clone(),
+                    target_datetime,
+                );
+                item.priority = self.form.priority;
+                item.tags = self.form.parsed_tags();
+                item.recurrence = self.form.recurrence;
+                item.notes = self.form.notes.clone();
+                self.items.push(item);
+                self.next_id += 1;
+                self.update_scroll_state();
+                self.mark_dirty();
+            }
+            AppMode::EditTask => {
+                if let Some(edit_id) = self.edit_id
+                    && let Some(index) = self.items.iter().position(|i| i.id == edit_id)
+                {
+                    self.push_undo(Some(edit_id));
+                    let item = &mut self.items[index];
+                    item.title = self.form.title.clone();
+                    item.description = self.form.description.clone();
+                    item.target_datetime = target_datetime;
+                    item.priority = self.form.priority;
+                    item.tags = self.form.parsed_tags();
+                    item.recurrence = self.form.recurrence;
+                    item.notes = self.form.notes.clone();
+                    item.touch();
+                    self.mark_dirty();
+                }
+            }
+            _ => {}
+        }
+        self.sort_items();
+        self.clamp_selection();
+        self.mode = AppMode::Normal;
+    }
+
+    fn cancel_form(&mut self) {
+        self.mode = AppMode::Normal;
+        self.form.clear();
+        self.edit_id = None;
+    }
+
+    fn get_progress(&self) -> (usize, usize) {
+        let active = self.items.iter().filter(|item| !item.archived);
+        let (completed, total) = active.fold((0, 0), |(completed, total), item| {
+            (completed + usize::from(item.completed), total + 1)
+        });
+        (completed, total)
+    }
+
+    /// Open tasks past their target date. Checked against the clock, so it stays right as
+    /// dates roll over while the app is open.
+    fn overdue_count(&self) -> usize {
+        self.items.iter().filter(|item| item.is_overdue()).count()
+    }
+
+    /// Mean days from creation to completion, over tasks whose completion date is known
+    fn average_completion_days(&self) -> Option<f64> {
+        let durations: Vec<i64> = self
+            .items
+            .iter()
+            .filter(|item| item.completed && !item.archived)
+            .filter_map(|item| Some((item.completed_date? - item.created_date).num_days()))
+            .collect();
+        if durations.is_empty() {
+            return None;
+        }
+        Some(durations.iter().sum::<i64>() as f64 / durations.len() as f64)
+    }
+
+    /// The figures on the statistics screen, over the tasks that aren't archived
+    fn compute_stats(&self) -> Stats {
+        let today = Local::now().date_naive();
+        let (completed, total) = self.get_progress();
+        let due_this_week = self
+            .items
+            .iter()
+            .filter(|item| !item.completed && !item.archived)
+            .filter(|item| (0..7).contains(&(item.target_datetime.date() - today).num_days()))
+            .count();
+        Stats {
+            total,
+            completed,
+            overdue: self.overdue_count(),
+            due_this_week,
+            completion_rate: if total > 0 { completed as f64 * 100.0 / total as f64 } else { 0.0 },
+            avg_days_to_complete: self.average_completion_days(),
+        }
+    }
+
+    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        #[cfg(feature = "watch")]
+        let watcher = match watch::SaveFileWatcher::new(&self.save_path) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                self.status = Some(Status::Error(format!("Not watching for changes: {}", err)));
+                None
+            }
+        };
+
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Wake up regularly for autosave and file changes instead of blocking on the next key
+            if !event::poll(TICK_INTERVAL)? {
+                #[cfg(feature = "watch")]
+                if watcher
+                    .as_ref()
+                    .is_some_and(|watcher| watcher.has_changed(&self.save_path))
+                {
+                    self.reload_external_change();
+                }
+                self.autosave();
+                continue;
+            }
+
+            let event = event::read()?;
+            if let Event::Mouse(mouse) = event {
+                self.handle_mouse(mouse);
+            }
+            // Text pasted through the terminal (bracketed paste) goes into the form's field
+            if let Event::Paste(text) = &event
+                && matches!(self.mode, AppMode::AddTask | AppMode::EditTask)
+                && !self.date_picker_open
+            {
+                self.form.insert_str(text);
+            }
+            if let Event::Key(key) = event
+                && key.kind == KeyEventKind::Press
+            {
+                // Any key dismisses a notice without triggering anything else
+                if self.notice.take().is_some() {
+                    continue;
+                }
+                self.status = None;
+                let quit_unsaved = std::mem::take(&mut self.quit_unsaved);
+                if self.save_conflict {
+                    self.resolve_conflict(key.code);
+                    continue;
+                }
+                let mode_before = self.mode;
+                match self.mode {
+                    AppMode::Normal => {
+                        match key.code {
+                            KeyCode::Esc if !self.selected_ids.is_empty() => self.selected_ids.clear(),
+                            KeyCode::Esc if self.search_query.is_some() => self.clear_search(),
+                            KeyCode::Esc if self.date_filter.is_some() => self.clear_date_filter(),
+                            KeyCode::Char('q') | KeyCode::Esc if self.quit(quit_unsaved) => return Ok(()),
+                            _ if self.read_only && modifies_tasks(&key) => self.report_read_only(),
+                            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.undo();
+                            }
+                            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.redo();
+                            }
+                            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.restore_backup();
+                            }
+                            // Ctrl as well, for terminals that keep Alt+arrows to themselves
+                            KeyCode::Down if key.modifiers.intersects(KeyModifiers::ALT | KeyModifiers::CONTROL) => {
+                                self.move_selected(false);
+                            }
+                            KeyCode::Up if key.modifiers.intersects(KeyModifiers::ALT | KeyModifiers::CONTROL) => {
+                                self.move_selected(true);
+                            }
+                            KeyCode::Down => self.next_row(),
+                            KeyCode::Up => self.previous_row(),
+                            KeyCode::Home => self.first_row(),
+                            KeyCode::End => self.last_row(),
+                            KeyCode::Left if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                                self.resize_focused_column(false);
+                            }
+                            KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                                self.resize_focused_column(true);
+                            }
+                            KeyCode::Left => self.focus_column(false),
+                            KeyCode::Right => self.focus_column(true),
+                            KeyCode::Char(' ') => self.toggle_completed(),
+                            KeyCode::Char('n') | KeyCode::Char('N') => self.start_add_task(),
+                            KeyCode::Char('e') | KeyCode::Char('E') => self.start_edit_task(),
+                            KeyCode::Char('d') | KeyCode::Char('D') => self.start_delete(),
+                            KeyCode::Char('s') => self.set_sort_mode(SortMode::CreatedDate),
+                            KeyCode::Char('S') => self.mode = AppMode::Stats,
+                            KeyCode::Char('t') | KeyCode::Char('T') => self.set_sort_mode(SortMode::TargetDate),
+                            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.copy_title();
+                            }
+                            KeyCode::Char('c') | KeyCode::Char('C') => self.set_sort_mode(SortMode::Completion),
+                            KeyCode::Char('o') => self.set_sort_mode(SortMode::Priority),
+                            KeyCode::Char('O') => self.start_notes_editor(),
+                            KeyCode::Char('R') => self.toggle_sort_direction(),
+                            KeyCode::Char('r') => self.start_reload(),
+                            KeyCode::Char('p') => self.cycle_priority(),
+                            KeyCode::Char('P') => self.start_project_switcher(),
+                            KeyCode::Char('[') => self.cycle_project(false),
+                            KeyCode::Char(']') => self.cycle_project(true),
+                            KeyCode::Enter => self.toggle_expanded(),
+                            KeyCode::Char('+') => self.start_add_subtask(),
+                            KeyCode::Char('-') => self.clear_done_subtasks(),
+                            KeyCode::Char(c @ '1'..='9') => {
+                                self.toggle_subtask(c.to_digit(10).unwrap_or_default() as usize);
+                            }
+                            KeyCode::Char('{') => self.jump_to_overdue(false),
+                            KeyCode::Char('}') => self.jump_to_overdue(true),
+                            KeyCode::Char('/') => self.start_search(),
+                            KeyCode::Char('?') => self.mode = AppMode::Help,
+                            KeyCode::Char('g') | KeyCode::Char('G') => self.cycle_tag_filter(),
+                            KeyCode::Char('v') | KeyCode::Char('V') => self.toggle_marked(),
+                            KeyCode::Char('x') | KeyCode::Char('X') => self.export_csv(),
+                            KeyCode::Char('m') | KeyCode::Char('M') => self.export_markdown(),
+                            KeyCode::Char('L') => self.export_ical(),
+                            KeyCode::Char('I') => self.start_import(),
+                            KeyCode::Char('W') => self.start_calendar(),
+                            KeyCode::Char('A') => self.start_archive(),
+                            KeyCode::Char('f') | KeyCode::Char('F') => self.archive_selected(),
+                            KeyCode::Char('a') => self.open_archive_view(),
+                            KeyCode::Char('b') | KeyCode::Char('B') => self.open_trash_view(),
+                            KeyCode::Char('h') => self.show_history(),
+                            KeyCode::Char('H') => self.toggle_hide_completed(),
+                            _ => {}
+                        }
+                    }
+                    AppMode::AddTask | AppMode::EditTask if self.date_picker_open => {
+                        match key.code {
+                            KeyCode::Esc => self.date_picker_open = false,
+                            KeyCode::Enter => self.pick_date(),
+                            KeyCode::Left => self.move_calendar_days(-1),
+                            KeyCode::Right => self.move_calendar_days(1),
+                            KeyCode::Up => self.move_calendar_days(-7),
+                            KeyCode::Down => self.move_calendar_days(7),
+                            KeyCode::Char('[') | KeyCode::PageUp => self.move_calendar_month(false),
+                            KeyCode::Char(']') | KeyCode::PageDown => self.move_calendar_month(true),
+                            _ => {}
+                        }
+                    }
+                    AppMode::AddTask | AppMode::EditTask => {
+                        self.form.error = None;
+                        // Only a submit straight after the warning confirms the duplicate
+                        let confirmed = std::mem::take(&mut self.form.duplicate_warned);
+                        match key.code {
+                            KeyCode::Esc => self.cancel_form(),
+                            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.submit_form(confirmed);
+                            }
+                            // The description and notes are multi-line, so Enter adds a line break there
+                            KeyCode::Enter if matches!(self.form.field_index, 1 | 6) => {
+                                self.form.insert_char('\n');
+                            }
+                            KeyCode::Enter => self.submit_form(confirmed),
+                            KeyCode::F(2) if self.form.field_index == 2 => self.open_date_picker(),
+                            KeyCode::Tab => self.form.next_field(),
+                            KeyCode::BackTab => self.form.prev_field(),
+                            KeyCode::Left if self.form.field_index == 3 => {
+                                self.form.priority = self.form.priority.prev();
+                            }
+                            KeyCode::Right if self.form.field_index == 3 => {
+                                self.form.priority = self.form.priority.next();
+                            }
+                            KeyCode::Left if self.form.field_index == 4 => {
+                                self.form.recurrence = Recurrence::prev(self.form.recurrence);
+                            }
+                            KeyCode::Right if self.form.field_index == 4 => {
+                                self.form.recurrence = Recurrence::next(self.form.recurrence);
+                            }
+                            KeyCode::Left => self.form.move_cursor_left(),
+                            KeyCode::Right => self.form.move_cursor_right(),
+                            KeyCode::Home => self.form.move_cursor_home(),
+                            KeyCode::End => self.form.move_cursor_end(),
+                            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                // Nothing happens where the clipboard can't be read
+                                if let Some(text) = clipboard::paste() {
+                                    self.form.insert_str(&text);
+                                }
+                            }
+                            KeyCode::Char(c) => self.form.insert_char(c),
+                            KeyCode::Backspace => self.form.delete_before_cursor(),
+                            KeyCode::Delete => self.form.delete_at_cursor(),
+                            _ => {}
+                        }
+                    }
+                    AppMode::ProjectSwitcher => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::Normal,
+                            KeyCode::Down => {
+                                self.project_selected = (self.project_selected + 1) % self.projects.len();
+                            }
+                            KeyCode::Up => {
+                                self.project_selected = self
+                                    .project_selected
+                                    .checked_sub(1)
+                                    .unwrap_or(self.projects.len() - 1);
+                            }
+                            KeyCode::Enter => {
+                                let path = self.projects[self.project_selected].clone();
+                                self.open_project(path);
+                            }
+                            KeyCode::Char('N') => self.start_new_project(),
+                            _ => {}
+                        }
+                    }
+                    AppMode::AddSubtask => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::Normal,
+                            KeyCode::Enter => self.add_subtask(),
+                            KeyCode::Char(c) => self.subtask_input.push(c),
+                            KeyCode::Backspace => {
+                                self.subtask_input.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::NewProject => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::ProjectSwitcher,
+                            KeyCode::Enter => self.create_project(),
+                            KeyCode::Char(c) => self.project_name.push(c),
+                            KeyCode::Backspace => {
+                                self.project_name.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::ConfirmReload => {
+                        match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                self.mode = AppMode::Normal;
+                                self.reload_from_disk();
+                            }
+                            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                                self.mode = AppMode::Normal;
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::ConfirmArchive => {
+                        match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                self.mode = AppMode::Normal;
+                                self.archive_completed();
+                            }
+                            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                                self.mode = AppMode::Normal;
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::ArchiveView => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('a') => self.mode = AppMode::Normal,
+                            KeyCode::Down => {
+                                let count = self.archive_len();
+                                step_selection(&mut self.archive_state, count, true);
+                            }
+                            KeyCode::Up => {
+                                let count = self.archive_len();
+                                step_selection(&mut self.archive_state, count, false);
+                            }
+                            KeyCode::Char('u') | KeyCode::Char('U') => self.unarchive_selected(),
+                            _ => {}
+                        }
+                    }
+                    AppMode::TrashView => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('b') | KeyCode::Char('B') => self.mode = AppMode::Normal,
+                            KeyCode::Down => step_selection(&mut self.trash_state, self.trash.len(), true),
+                            KeyCode::Up => step_selection(&mut self.trash_state, self.trash.len(), false),
+                            KeyCode::Enter | KeyCode::Char('r') | KeyCode::Char('R') => self.restore_from_trash(),
+                            KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => self.purge_from_trash(),
+                            _ => {}
+                        }
+                    }
+                    AppMode::ConfirmDelete => {
+                        match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                                self.delete_pending();
+                                self.mode = AppMode::Normal;
+                            }
+                            // Anything else backs out, so a stray key never deletes
+                            _ => {
+                                self.pending_delete = None;
+                                self.mode = AppMode::Normal;
+                            }
+                        }
+                    }
+                    AppMode::Import => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::Normal,
+                            KeyCode::Enter => self.preview_import(),
+                            KeyCode::Char(c) => self.import_path.push(c),
+                            KeyCode::Backspace => {
+                                self.import_path.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::ConfirmLock => self.resolve_lock(key.code),
+                    AppMode::ConfirmImport => {
+                        match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.confirm_import(),
+                            _ => {
+                                self.mode = AppMode::Normal;
+                                self.import_preview = None;
+                            }
+                        }
+                    }
+                    AppMode::NotesEditor => {
+                        match key.code {
+                            KeyCode::Esc => {
+                                self.mode = AppMode::Normal;
+                                self.edit_id = None;
+                            }
+                            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.save_notes();
+                            }
+                            KeyCode::Enter => self.notes_input.push('\n'),
+                            KeyCode::Tab => self.notes_input.push_str("    "),
+                            KeyCode::Char(c) => self.notes_input.push(c),
+                            KeyCode::Backspace => {
+                                self.notes_input.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                    AppMode::Stats => {
+                        if matches!(key.code, KeyCode::Esc | KeyCode::Char('S') | KeyCode::Char('q')) {
+                            self.mode = AppMode::Normal;
+                        }
+                    }
+                    // Any key closes the help overlay
+                    AppMode::Help => self.mode = AppMode::Normal,
+                    AppMode::ContextMenu => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::Normal,
+                            KeyCode::Down => {
+                                self.context_menu_selected =
+                                    (self.context_menu_selected + 1) % CONTEXT_MENU_ITEMS.len();
+                            }
+                            KeyCode::Up => {
+                                self.context_menu_selected = self
+                                    .context_menu_selected
+                                    .checked_sub(1)
+                                    .unwrap_or(CONTEXT_MENU_ITEMS.len() - 1);
+                            }
+                            KeyCode::Enter => self.run_context_menu_item(self.context_menu_selected),
+                            _ => {}
+                        }
+                    }
+                    AppMode::CalendarView => {
+                        match key.code {
+                            KeyCode::Esc => self.mode = AppMode::Normal,
+                            KeyCode::Enter => self.filter_to_calendar_day(),
+                            KeyCode::Left => self.move_calendar_days(-1),
+                            KeyCode::Right => self.move_calendar_days(1),
+                            KeyCode::Up => self.move_calendar_days(-7),
+                            KeyCode::Down => self.move_calendar_days(7),
+                            KeyCode::Char('[') => self.move_calendar_month(false),
+                            KeyCode::Char(']') => self.move_calendar_month(true),
+                            _ => {}
+                        }
+                    }
+                    AppMode::Search => {
+                        match key.code {
+                            KeyCode::Esc => self.clear_search(),
+                            KeyCode::Enter => self.lock_search(),
+                            KeyCode::Down => self.next_row(),
+                            KeyCode::Up => self.previous_row(),
+                            KeyCode::Home => self.first_row(),
+                            KeyCode::End => self.last_row(),
+                            KeyCode::Char(c) => {
+                                self.search_query.get_or_insert_default().push(c);
+                                self.state.select(Some(0));
+                                self.clamp_selection();
+                            }
+                            KeyCode::Backspace => {
+                                if let Some(query) = &mut self.search_query {
+                                    query.pop();
+                                }
+                                self.state.select(Some(0));
+                                self.clamp_selection();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                // Closing a form or popup is a natural point to get pending changes on disk
+                if self.mode != mode_before {
+                    self.flush();
+                }
+            }
+            self.autosave();
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.notice.is_some() || self.save_conflict {
+            return;
+        }
+        let position = Position::new(mouse.column, mouse.row);
+        match (self.mode, mouse.kind) {
+            (AppMode::Normal, MouseEventKind::Down(MouseButton::Left)) => {
+                let Some(row) = self.row_at(position) else {
+                    return;
+                };
+                self.state.select(Some(row));
+                if self.column_at(position.x) == Some(STATUS_COLUMN) {
+                    // A click on the status cell flips just that task, even with others marked
+                    self.last_click = None;
+                    if self.read_only {
+                        self.report_read_only();
+                    } else if let Some(selected) = self.selected_index() {
+                        self.push_undo(Some(self.items[selected].id));
+                        self.toggle_item(selected);
+                        self.update_scroll_state();
+                        self.mark_dirty();
+                    }
+                    return;
+                }
+                let double_click = self
+                    .last_click
+                    .is_some_and(|(last_row, at)| last_row == row && at.elapsed() <= DOUBLE_CLICK_INTERVAL);
+                if double_click {
+                    self.last_click = None;
+                    if self.read_only {
+                        self.report_read_only();
+                    } else {
+                        self.start_edit_task();
+                    }
+                } else {
+                    self.last_click = Some((row, Instant::now()));
+                }
+            }
+            (AppMode::Normal, MouseEventKind::Down(MouseButton::Right)) => {
+                let Some(row) = self.row_at(position) else {
+                    return;
+                };
+                self.state.select(Some(row));
+                self.open_context_menu(position);
+            }
+            (AppMode::Normal, MouseEventKind::ScrollDown) => self.next_row(),
+            (AppMode::Normal, MouseEventKind::ScrollUp) => self.previous_row(),
+            (AppMode::ContextMenu, MouseEventKind::Down(MouseButton::Left)) => {
+                match self.context_menu_item_at(position) {
+                    Some(item) => self.run_context_menu_item(item),
+                    None => self.mode = AppMode::Normal,
+                }
+            }
+            (AppMode::ContextMenu, MouseEventKind::Down(_)) => self.mode = AppMode::Normal,
+            _ => {}
+        }
+    }
+
+    /// Visible row under a screen position, if it is on a task rather than the header or
+    /// the empty space below the last task
+    fn row_at(&self, position: Position) -> Option<usize> {
+        if !self.table_area.contains(position) || position.y == self.table_area.y {
+            return None;
+        }
+        // Expanded tasks take up a line per subtask as well
+        let mut top = self.table_area.y + 1;
+        for (row, &i) in self.visible_indices().iter().enumerate().skip(self.state.offset()) {
+            top += self.row_height(&self.items[i]);
+            if position.y < top {
+                return Some(row);
+            }
+        }
+        None
+    }
+
+    /// Table column under a screen x position, laid out the way `Table` lays out its columns
+    fn column_at(&self, x: u16) -> Option<usize> {
+        Layout::horizontal(self.table_constraints())
+            .spacing(1)
+            .split(self.table_area)
+            .iter()
+            .position(|column| column.left() <= x && x < column.right())
+            // The mark gutter isn't one of `COLUMN_TITLES`
+            .and_then(|column| column.checked_sub(1))
+    }
+
+    /// Widths of the table's columns: the mark gutter, then `COLUMN_TITLES`
+    fn table_constraints(&self) -> Vec<Constraint> {
+        std::iter::once(Constraint::Length(MARK_COLUMN_WIDTH))
+            .chain(self.column_widths.to_array().map(Constraint::Percentage))
+            .collect()
+    }
+
+    /// Opens the menu with its top-left corner at the click, moved in to fit on screen
+    fn open_context_menu(&mut self, position: Position) {
+        let width = (CONTEXT_MENU_ITEMS.iter().map(|item| item.len()).max().unwrap_or(0) as u16 + 4)
+            .min(self.screen_area.width);
+        let height = (CONTEXT_MENU_ITEMS.len() as u16 + 2).min(self.screen_area.height);
+        self.context_menu_area = Rect {
+            x: position.x.min(self.screen_area.right().saturating_sub(width)),
+            y: position.y.min(self.screen_area.bottom().saturating_sub(height)),
+            width,
+            height,
+        };
+        self.context_menu_selected = 0;
+        self.mode = AppMode::ContextMenu;
+    }
+
+    fn context_menu_item_at(&self, position: Position) -> Option<usize> {
+        let inner = self.context_menu_area.inner(Margin::new(1, 1));
+        inner
+            .contains(position)
+            .then(|| usize::from(position.y - inner.y))
+            .filter(|&item| item < CONTEXT_MENU_ITEMS.len())
+    }
+
+    fn run_context_menu_item(&mut self, item: usize) {
+        self.mode = AppMode::Normal;
+        if self.read_only {
+            self.report_read_only();
+            return;
+        }
+        match CONTEXT_MENU_ITEMS[item] {
+            "Delete" => self.start_delete(),
+            "Toggle Complete" => self.toggle_completed(),
+            _ => self.start_edit_task(),
+        }
+    }
+
+    fn copy_title(&mut self) {
+        let Some(selected) = self.selected_index() else {
+            return;
+        };
+        let title = &self.items[selected].title;
+        self.status = Some(match clipboard::copy(title) {
+            Ok(()) => Status::Info(format!("Copied '{}'", title)),
+            Err(err) => Status::Error(format!("Failed to copy: {}", err.kind())),
+        });
+    }
+
+    fn report_read_only(&mut self) {
+        self.status = Some(Status::Error(
+            "Read-only: another rtodo has this file open".to_string(),
+        ));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        self.screen_area = frame.area();
+        let main_layout = Layout::vertical([
+            Constraint::Length(3), // Progress bar
+                                           Constraint::Min(5),    // Table
+                                           Constraint::Length(5), // Footer
+        ]);
+        let chunks = main_layout.split(frame.area());
+
+        self.render_progress_bar(frame, chunks[0]);
+        if self.mode == AppMode::CalendarView {
+            self.render_calendar(frame, chunks[1]);
+        } else if self.mode == AppMode::ArchiveView {
+            self.render_archive(frame, chunks[1]);
+        } else if self.mode == AppMode::TrashView {
+            self.render_trash(frame, chunks[1]);
+        } else if self.mode == AppMode::Stats {
+            self.render_stats(frame, chunks[1]);
+        } else if chunks[1].width > DETAIL_PANE_MIN_WIDTH {
+            let [table_area, detail_area] =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(chunks[1]);
+            self.render_table(frame, table_area);
+            self.render_detail_pane(frame, detail_area);
+        } else {
+            self.render_table(frame, chunks[1]);
+        }
+        self.render_footer(frame, chunks[2]);
+
+        if self.mode == AppMode::AddTask || self.mode == AppMode::EditTask {
+            self.render_form_popup(frame);
+            if self.date_picker_open {
+                self.render_date_picker(frame);
+            }
+        }
+        if self.mode == AppMode::ConfirmDelete {
+            self.render_confirm_delete_popup(frame);
+        }
+        if self.mode == AppMode::ProjectSwitcher {
+            self.render_project_switcher(frame);
+        }
+        if self.mode == AppMode::NewProject {
+            self.render_new_project_popup(frame);
+        }
+        if self.mode == AppMode::AddSubtask {
+            self.render_add_subtask_popup(frame);
+        }
+        if self.mode == AppMode::ConfirmReload {
+            render_confirm_popup(
+                frame,
+                "Confirm Reload",
+                "Changes that could not be saved will be lost. Reload from disk? (y/n)",
+                &self.config.theme,
+            );
+        }
+        if self.mode == AppMode::ConfirmArchive {
+            let count = self.items.iter().filter(|item| item.completed).count();
+            render_confirm_popup(
+                frame,
+                "Confirm Archive",
+                &format!(
+                    "Move {} completed tasks to {}? (y/n)",
+                    count,
+                    storage::archive_path(&self.save_path).display()
+                ),
+                &self.config.theme,
+            );
+        }
+        if self.mode == AppMode::Import {
+            self.render_import_popup(frame);
+        }
+        if self.mode == AppMode::ConfirmImport
+            && let Some(result) = &self.import_preview
+        {
+            render_confirm_popup(
+                frame,
+                "Confirm Import",
+                &format!(
+                    "Add {} tasks from {} ({} skipped)? (y/n)",
+                    result.items.len(),
+                    self.import_path.trim(),
+                    result.errors.len()
+                ),
+                &self.config.theme,
+            );
+        }
+        if self.mode == AppMode::ConfirmLock
+            && let Some(locked) = &self.locked_project
+        {
+            render_confirm_popup(
+                frame,
+                "Project Locked",
+                &lock_prompt(&locked.path, locked.pid, locked.running, "cancel"),
+                &self.config.theme,
+            );
+        }
+        if self.mode == AppMode::ContextMenu {
+            self.render_context_menu(frame);
+        }
+        if self.mode == AppMode::Help {
+            render_help_popup(frame, &self.config.theme);
+        }
+        if self.mode == AppMode::NotesEditor {
+            self.render_notes_editor(frame);
+        }
+        if self.save_conflict {
+            self.render_conflict_popup(frame);
+        }
+        if let Some(notice) = &self.notice {
+            render_notice_popup(frame, notice, &self.config.theme);
+        }
+    }
+
+    fn render_detail_pane(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("Details");
+        let Some(item) = self.selected_index().map(|i| &self.items[i]) else {
+            let empty = Paragraph::new("No task selected")
+                .style(Style::default().fg(self.config.theme.muted_fg))
+                .block(block);
+            frame.render_widget(empty, area);
+            return;
+        };
+
+        let label = Style::default().fg(self.config.theme.accent_fg);
+        let mut lines = vec![
+            Line::from(Span::styled(item.title.clone(), Style::default().add_modifier(Modifier::BOLD))),
+            Line::from(""),
+        ];
+        if item.description.is_empty() {
+            lines.push(Line::from(Span::styled("No description", Style::default().fg(self.config.theme.muted_fg))));
+        } else {
+            lines.extend(item.description.lines().map(|line| Line::from(line.to_string())));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Created:  ", label),
+            Span::raw(item.created_date.format("%Y-%m-%d").to_string()),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Target:   ", label),
+            Span::raw(item.target_datetime.format("%Y-%m-%d %H:%M").to_string()),
+            Span::raw(" "),
+            Span::styled(item.due_label(), Style::default().fg(item.get_status_color(&self.config))),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Priority: ", label),
+            Span::styled("● ", Style::default().fg(item.priority.color(&self.config.theme))),
+            Span::raw(item.priority.label()),
+        ]));
+        if let Some(recurrence) = item.recurrence {
+            lines.push(Line::from(vec![
+                Span::styled("Repeats:  ", label),
+                Span::raw(Recurrence::label(Some(recurrence))),
+            ]));
+        }
+        if !item.tags.is_empty() {
+            let mut tags = vec![Span::styled("Tags:     ", label)];
+            tags.extend(item.tag_spans(&self.config.theme));
+            lines.push(Line::from(tags));
+        }
+        if !item.subtasks.is_empty() {
+            const BAR_WIDTH: usize = 10;
+            let (done, total) = item.subtask_progress();
+            let filled = done * BAR_WIDTH / total;
+            lines.push(Line::from(vec![
+                Span::styled("Subtasks: ", label),
+                Span::styled("█".repeat(filled), Style::default().fg(self.config.theme.completed_fg)),
+                Span::styled("░".repeat(BAR_WIDTH - filled), Style::default().fg(self.config.theme.muted_fg)),
+                Span::raw(format!(" {}/{}", done, total)),
+            ]));
+            lines.extend(item.subtasks.iter().map(|subtask| {
+                Line::from(format!(
+                    "  {} {}",
+                    if subtask.completed { "☑" } else { "☐" },
+                    subtask.title
+                ))
+            }));
+        }
+        if !item.notes.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Notes:", label)));
+            lines.extend(item.notes.lines().map(|line| Line::from(line.to_string())));
+        }
+
+        let detail = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block);
+        frame.render_widget(detail, area);
+    }
+
+    fn render_progress_bar(&self, frame: &mut Frame, area: Rect) {
+        let (completed, total) = self.get_progress();
+        let progress = if total > 0 { completed as f64 / total as f64 } else { 0.0 };
+
+        let mut progress_text = format!("Progress: {}/{} tasks completed", completed, total);
+        if let Some(days) = self.average_completion_days() {
+            progress_text.push_str(&format!(" | avg {:.1} days to complete", days));
+        }
+        if self.hide_completed && completed > 0 {
+            progress_text.push_str(&format!(" ({} hidden)", completed));
+        }
+        let overdue = match self.overdue_count() {
+            0 => Span::styled("✓", Style::default().fg(self.config.theme.completed_fg)),
+            count => Span::styled(
+                format!("⚠ {} overdue", count),
+                Style::default().fg(self.config.theme.error_fg).add_modifier(Modifier::BOLD),
+            ),
+        };
+        let title = Line::from(vec![Span::raw("Todo Progress "), overdue]);
+        let gauge = Gauge::default()
+        .block(Block::bordered().title(title))
+        .gauge_style(Style::default().fg(progress_color(progress, &self.config.theme)).bg(self.config.theme.background))
+        .percent((progress * 100.0) as u16)
+        .label(progress_text);
+
+        frame.render_widget(gauge, area);
+    }
+
+    fn render_table(&mut self, frame: &mut Frame, area: Rect) {
+        let header = std::iter::once(Cell::from(""))
+        .chain(COLUMN_TITLES.into_iter().enumerate().map(|(i, title)| {
+            if i == self.focused_column {
+                Cell::from(title).style(Style::default().add_modifier(Modifier::UNDERLINED))
+            } else {
+                Cell::from(title)
+            }
+        }))
+        .collect::<Row>()
+        .style(Style::default().fg(self.config.theme.header_fg).add_modifier(Modifier::BOLD))
+        .height(1);
+
+        // Widths the free-text columns will get, so overflowing text can end in an ellipsis
+        // instead of being cut off mid-word without a hint
+        let column_widths: Vec<usize> = Layout::horizontal(self.table_constraints())
+            .spacing(1)
+            .split(Block::bordered().inner(area))
+            .iter()
+            .skip(1)
+            .map(|column| usize::from(column.width))
+            .collect();
+
+        let rows = self.visible_indices().into_iter().map(|i| {
+            let item = &self.items[i];
+            let mut status = match (item.completed, item.completed_date) {
+                (true, Some(date)) => format!("✓ {}", date.format("%Y-%m-%d")),
+                (true, None) => "✓ Done".to_string(),
+                (false, _) => "○ Pending".to_string(),
+            };
+            if item.recurrence.is_some() {
+                status.push_str(" ↻");
+            }
+            let mut title = vec![Line::from(truncate_with_ellipsis(&item.title, column_widths[0]))];
+            if !item.subtasks.is_empty() {
+                let (done, total) = item.subtask_progress();
+                status.push_str(&format!(" {}/{}", done, total));
+                let expanded = self.expanded.contains(&item.id);
+                title[0] = Line::from(truncate_with_ellipsis(
+                    &format!("{} {}", if expanded { "▾" } else { "▸" }, item.title),
+                    column_widths[0],
+                ));
+                if expanded {
+                    title.extend(item.subtasks.iter().enumerate().map(|(n, subtask)| {
+                        let line = format!(
+                            "  {} {} {}",
+                            n + 1,
+                            if subtask.completed { "☑" } else { "☐" },
+                            subtask.title
+                        );
+                        Line::from(truncate_with_ellipsis(&line, column_widths[0])).style(
+                            if subtask.completed {
+                                Style::default().add_modifier(Modifier::DIM)
+                            } else {
+                                Style::default()
+                            },
+                        )
+                    }));
+                }
+            }
+            let status_color = item.get_status_color(&self.config);
+            // Check boxes only show up once something is marked
+            let mark = match (self.selected_ids.contains(&item.id), self.selected_ids.is_empty()) {
+                (true, _) => "☑",
+                (false, false) => "☐",
+                (false, true) => "",
+            };
+
+            Row::new(vec![
+                Cell::from(mark),
+                Cell::from(Text::from(title)),
+                     Cell::from(truncate_with_ellipsis(&item.description_summary(), column_widths[1])),
+                     Cell::from(Line::from(item.tag_spans(&self.config.theme))),
+                     Cell::from(date_utils::format_deadline(item.target_datetime)),
+                     Cell::from(item.created_date.format("%Y-%m-%d").to_string()),
+                     Cell::from(Line::from(vec![
+                         Span::styled("● ", Style::default().fg(item.priority.color(&self.config.theme))),
+                         Span::raw(item.priority.label()),
+                     ])),
+                     Cell::from(status).style(Style::default().fg(status_color)),
+            ])
+            .style(if self.selected_ids.contains(&item.id) {
+                item.get_row_style(&self.config).bg(self.config.theme.selected_bg)
+            } else {
+                item.get_row_style(&self.config)
+            })
+            .height(self.row_height(item))
+        });
+
+        let sort_name = match self.sort_mode {
+            SortMode::CreatedDate => "Date",
+            SortMode::TargetDate => "Target",
+            SortMode::Completion => "Status",
+            SortMode::Priority => "Priority",
+            SortMode::Manual => "Manual",
+        };
+        let sort_indicator = if self.sort_mode == SortMode::Manual {
+            " [Manual order]".to_string()
+        } else {
+            format!(
+                " [Sorted by {} {}]",
+                sort_name,
+                if self.sort_ascending { "↑" } else { "↓" }
+            )
+        };
+        let marked_indicator = if self.selected_ids.is_empty() {
+            String::new()
+        } else {
+            format!(" [{} selected]", self.selected_ids.len())
+        };
+        let mut filter_indicator = match self.search_query.as_deref() {
+            None | Some("") => String::new(),
+            Some(query) if query.starts_with('#') => format!(" [{}]", query),
+            Some(query) => format!(" [Filter: {}]", query),
+        };
+        if let Some(day) = self.date_filter {
+            filter_indicator.push_str(&format!(" [Due {}]", day.format("%Y-%m-%d")));
+        }
+        if self.hide_completed {
+            filter_indicator.push_str(" [Pending only]");
+        }
+
+        let table = Table::new(rows, self.table_constraints())
+        .header(header)
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_spacing(HighlightSpacing::Always);
+
+        let block = Block::bordered().title(format!(
+            "Todo List: {}{}{}{}{}",
+            self.current_project,
+            if self.dirty { "*" } else { "" },
+            sort_indicator,
+            filter_indicator,
+            marked_indicator
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        // While searching, the query input sits above the header row
+        let table_area = if self.mode == AppMode::Search {
+            let [search_area, table_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+            self.render_search_input(frame, search_area);
+            table_area
+        } else {
+            inner
+        };
+
+        self.table_area = table_area;
+        frame.render_stateful_widget(table, table_area, &mut self.state);
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let info_text = Text::from_iter(INFO_TEXT.iter().map(|&s| Line::from(s)));
+        let mut block = Block::bordered()
+            .title("Controls")
+            .title_bottom(
+                Line::from(format!(
+                    " {}{} ",
+                    self.save_path.display(),
+                    if self.read_only { " [read-only]" } else { "" }
+                ))
+                .right_aligned(),
+            );
+        if let Some(status) = &self.status {
+            let (text, style) = match status {
+                Status::Info(text) => (text, Style::default().fg(self.config.theme.completed_fg)),
+                Status::Error(text) => (text, Style::default().fg(self.config.theme.error_fg).add_modifier(Modifier::BOLD)),
+            };
+            block = block.title(Line::from(format!(" {} ", text)).style(style).right_aligned());
+        }
+        let footer = Paragraph::new(info_text)
+        .style(Style::default().fg(self.config.theme.hint_fg))
+        .block(block)
+        .alignment(Alignment::Center);
+
+        frame.render_widget(footer, area);
+    }
+
+    /// Month grid with the number of tasks due each day: green when they are all done,
+    /// red when any is overdue
+    fn render_calendar(&self, frame: &mut Frame, area: Rect) {
+        let day = self.calendar_day;
+        let first = day.with_day(1).unwrap_or(day);
+        let days_in_month = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map_or(31, |last| last.day());
+        let offset = first.weekday().num_days_from_monday();
+        let weeks = (offset + days_in_month).div_ceil(7);
+
+        let block = Block::bordered()
+            .title(format!("Calendar: {}", first.format("%B %Y")))
+            .title_bottom(
+                Line::from(" ←/→/↑/↓: move | [/]: month | Enter: show tasks | Esc: back ")
+                    .right_aligned(),
+            );
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [header_area, grid_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        let columns = Layout::horizontal([Constraint::Fill(1); 7]);
+        for (name, cell) in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .into_iter()
+            .zip(columns.split(header_area).iter())
+        {
+            let header = Paragraph::new(name)
+                .style(Style::default().fg(self.config.theme.header_fg).add_modifier(Modifier::BOLD))
+                .alignment(Alignment::Center);
+            frame.render_widget(header, *cell);
+        }
+
+        let today = Local::now().date_naive();
+        let rows = Layout::vertical((0..weeks).map(|_| Constraint::Fill(1))).split(grid_area);
+        for (week, row) in rows.iter().enumerate() {
+            for (weekday, cell) in columns.split(*row).iter().enumerate() {
+                let Some(number) = (week * 7 + weekday + 1)
+                    .checked_sub(offset as usize)
+                    .filter(|n| (1..=days_in_month as usize).contains(n))
+                else {
+                    continue;
+                };
+                let Some(date) = first.with_day(number as u32) else {
+                    continue;
+                };
+
+                let due: Vec<&TodoItem> = self
+                    .items
+                    .iter()
+                    .filter(|item| !item.archived && item.target_datetime.date() == date)
+                    .collect();
+                let badge_color = if due.iter().any(|item| item.is_overdue()) {
+                    self.config.theme.error_fg
+                } else if due.iter().all(|item| item.completed) {
+                    self.config.theme.completed_fg
+                } else {
+                    self.config.theme.pending_fg
+                };
+                let badge = match due.len() {
+                    0 => Line::default(),
+                    1 => Line::from("1 task").style(Style::default().fg(badge_color)),
+                    n => Line::from(format!("{} tasks", n)).style(Style::default().fg(badge_color)),
+                };
+
+                let mut cell_block = Block::bordered().title(number.to_string());
+                if date == today {
+                    cell_block = cell_block.title_style(Style::default().add_modifier(Modifier::BOLD));
+                }
+                if date == day {
+                    cell_block = cell_block.border_style(Style::default().fg(self.config.theme.accent_fg));
+                }
+                frame.render_widget(Paragraph::new(badge).block(cell_block), *cell);
+            }
+        }
+    }
+
+    fn render_archive(&mut self, frame: &mut Frame, area: Rect) {
+        let header = ["Title", "Description", "Tags", "Target Date", "Completed"]
+            .into_iter()
+            .map(Cell::from)
+            .collect::<Row>()
+            .style(Style::default().fg(self.config.theme.header_fg).add_modifier(Modifier::BOLD));
+        let archived = self.archived_indices();
+        let count = archived.len() + self.archive_items.len();
+        let in_list = archived.iter().map(|&i| &self.items[i]);
+        let rows = in_list.chain(self.archive_items.iter()).map(|item| {
+            Row::new(vec![
+                Cell::from(item.title.clone()),
+                Cell::from(item.description_summary()),
+                Cell::from(Line::from(item.tag_spans(&self.config.theme))),
+                Cell::from(date_utils::format_deadline(item.target_datetime)),
+                Cell::from(
+                    item.completed_date
+                        .map_or("✓ Done".to_string(), |date| format!("✓ {}", date.format("%Y-%m-%d"))),
+                ),
+            ])
+            .style(Style::default().fg(self.config.theme.hint_fg))
+        });
+
+        let table = Table::new(rows, [25, 35, 15, 13, 12].map(Constraint::Percentage))
+            .header(header)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_spacing(HighlightSpacing::Always)
+            .block(
+                Block::bordered()
+                    .title(format!(
+                        "Archive: {} ({} tasks, read-only)",
+                        self.current_project,
+                        count
+                    ))
+                    .title_bottom(Line::from(" ↑/↓: move | U: restore to list | Esc: back ").right_aligned()),
+            );
+        frame.render_stateful_widget(table, area, &mut self.archive_state);
+    }
+
+    /// Totals for the open list and a chart of completions over the last two weeks,
+    /// recomputed on every frame
+    fn render_stats(&self, frame: &mut Frame, area: Rect) {
+        let today = Local::now().date_naive();
+        let stats = self.compute_stats();
+
+        let label = Style::default().fg(self.config.theme.accent_fg);
+        let metric = |name: &'static str, value: String| {
+            Line::from(vec![Span::styled(format!("{:<24}", name), label), Span::raw(value)])
+        };
+        let lines = vec![
+            metric("Total tasks", stats.total.to_string()),
+            metric("Completed", stats.completed.to_string()),
+            metric("Overdue", stats.overdue.to_string()).style(if stats.overdue > 0 {
+                Style::default().fg(self.config.theme.error_fg)
+            } else {
+                Style::default()
+            }),
+            metric("Completion rate", format!("{:.0}%", stats.completion_rate)),
+            metric(
+                "Avg days to complete",
+                stats.avg_days_to_complete
+                    .map_or("-".to_string(), |days| format!("{:.1}", days)),
+            ),
+            metric("Due in the next 7 days", stats.due_this_week.to_string()),
+        ];
+
+        let block = Block::bordered()
+            .title(format!("Statistics: {}", self.current_project))
+            .title_bottom(Line::from(" Esc: back ").right_aligned());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let [metrics_area, chart_area] =
+            Layout::horizontal([Constraint::Length(36), Constraint::Min(0)]).areas(inner);
+        frame.render_widget(Paragraph::new(lines), metrics_area.inner(Margin::new(1, 1)));
+
+        let bars: Vec<Bar> = (0..14)
+            .rev()
+            .filter_map(|days_ago| today.checked_sub_days(Days::new(days_ago)))
+            .map(|day| {
+                let count = self
+                    .items
+                    .iter()
+                    .filter(|item| item.completed_date == Some(day))
+                    .count();
+                Bar::default()
+                    .value(count as u64)
+                    .label(Line::from(day.format("%d").to_string()))
+            })
+            .collect();
+        let chart_block = Block::bordered().title("Completed per day (last 14 days)");
+        let bar_width = (chart_block.inner(chart_area).width / 14).saturating_sub(1).max(1);
+        let chart = BarChart::default()
+            .block(chart_block)
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(self.config.theme.completed_fg))
+            .value_style(Style::default().fg(self.config.theme.background).bg(self.config.theme.completed_fg));
+        frame.render_widget(chart, chart_area);
+    }
+
+    fn render_trash(&mut self, frame: &mut Frame, area: Rect) {
+        let header = ["Title", "Description", "Target Date", "Status", "Deleted"]
+            .into_iter()
+            .map(Cell::from)
+            .collect::<Row>()
+            .style(Style::default().fg(self.config.theme.header_fg).add_modifier(Modifier::BOLD));
+        let rows = self.trash.iter().map(|entry| {
+            Row::new(vec![
+                Cell::from(entry.task.title.clone()),
+                Cell::from(entry.task.description_summary()),
+                Cell::from(date_utils::format_deadline(entry.task.target_datetime)),
+                Cell::from(if entry.task.completed { "✓ Done" } else { "○ Pending" }),
+                Cell::from(entry.deleted_date.format("%Y-%m-%d").to_string()),
+            ])
+            .style(Style::default().fg(self.config.theme.hint_fg))
+        });
+
+        let table = Table::new(rows, [28, 32, 16, 12, 12].map(Constraint::Percentage))
+            .header(header)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_spacing(HighlightSpacing::Always)
+            .block(
+                Block::bordered()
+                    .title(format!(
+                        "Trash: {} ({} tasks, purged after {} days)",
+                        self.current_project,
+                        self.trash.len(),
+                        TRASH_RETENTION_DAYS
+                    ))
+                    .title_bottom(
+                        Line::from(" ↑/↓: move | Enter/R: restore | D: purge | Esc: back ").right_aligned(),
+                    ),
+            );
+        frame.render_stateful_widget(table, area, &mut self.trash_state);
+    }
+
+    fn render_search_input(&self, frame: &mut Frame, area: Rect) {
+        let input = Paragraph::new(Line::from(vec![
+            Span::styled("Search: ", Style::default().fg(self.config.theme.accent_fg)),
+            Span::raw(self.search_query.as_deref().unwrap_or_default()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            Span::styled("  (Enter: keep filter | Esc: clear)", Style::default().fg(self.config.theme.hint_fg)),
+        ]));
+
+        frame.render_widget(input, area);
+    }
+
+    fn render_form_popup(&self, frame: &mut Frame) {
+        let area = frame.area();
+        // Four 3-row rows (priority and recurrence share one), the 5-row description and
+        // notes, two rows of instructions and the popup border
+        let height = (4 * 3 + 2 * 5 + 2 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.width / 4,
+            y: (area.height - height) / 2,
+            width: area.width / 2,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let title = if self.mode == AppMode::AddTask {
+            "Add New Task"
+        } else {
+            "Edit Task"
+        };
+
+        let form_layout = Layout::vertical([
+            Constraint::Length(3),
+                                           Constraint::Length(5),
+                                           Constraint::Length(3),
+                                           Constraint::Length(3),
+                                           Constraint::Length(3),
+                                           Constraint::Length(5),
+                                           Constraint::Min(2),
+        ]);
+        let form_chunks = form_layout.split(popup_area.inner(Margin {
+            vertical: 1,
+            horizontal: 1,
+        }));
+
+        let popup_block = Block::bordered()
+        .title(title)
+        .style(Style::default().bg(self.config.theme.background));
+        frame.render_widget(popup_block, popup_area);
+
+        // Title field
+        let title_style = if self.form.field_index == 0 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        // Only a hint: long titles are still accepted, they just get cut off in the table
+        let title_length = self.form.title.chars().count();
+        let title_count = Span::styled(
+            format!("{}/{}", title_length, TITLE_LENGTH_WARNING),
+            if title_length > TITLE_LENGTH_WARNING {
+                Style::default().fg(self.config.theme.error_fg)
+            } else {
+                Style::default()
+            },
+        );
+        let title_input = Paragraph::new(text_with_cursor(&self.form.title, self.form.cursor_in(0)))
+        .block(
+            Block::bordered()
+                .title("Title")
+                .title(Line::from(title_count).right_aligned())
+                .style(title_style),
+        )
+        .style(title_style);
+        frame.render_widget(title_input, form_chunks[0]);
+
+        // Description field
+        let desc_style = if self.form.field_index == 1 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        // Keep the cursor's line of a long description in view
+        let desc_input = Paragraph::new(text_with_cursor(&self.form.description, self.form.cursor_in(1)))
+        .wrap(Wrap { trim: false })
+        .scroll((cursor_line(&self.form.description, self.form.cursors[1]).saturating_sub(2), 0))
+        .block(Block::bordered().title("Description (Enter: new line)").style(desc_style))
+        .style(desc_style);
+        frame.render_widget(desc_input, form_chunks[1]);
+
+        // Target date field
+        let date_style = if self.form.field_index == 2 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        // Flag the date after a rejected submit; the error clears on the next keystroke
+        let date_border_style = if self.form.error.is_some()
+            && date_utils::parse_datetime_input(&self.form.target_date).is_none()
+        {
+            Style::default().fg(self.config.theme.error_fg)
+        } else {
+            date_style
+        };
+        // Spell out what a shorthand like `+2w` resolves to, so it can be checked before saving
+        let mut date_text = text_with_cursor(&self.form.target_date, self.form.cursor_in(2));
+        if let Some(resolved) = date_utils::parse_datetime_input(&self.form.target_date)
+            && let Some(line) = date_text.lines.first_mut()
+        {
+            line.push_span(Span::styled(
+                resolved.format("  → %a %Y-%m-%d %H:%M").to_string(),
+                Style::default().fg(self.config.theme.muted_fg),
+            ));
+        }
+        let date_input = Paragraph::new(date_text)
+        .block(
+            Block::bordered()
+                .title("Target Date (YYYY-MM-DD [HH:MM], today, +3d, +2w, +1m; F2: calendar)")
+                .style(date_border_style),
+        )
+        .style(date_style);
+        frame.render_widget(date_input, form_chunks[2]);
+
+        // Priority field
+        let priority_style = if self.form.field_index == 3 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        let priority_input = Paragraph::new(Line::from(vec![
+            Span::raw("◀ "),
+            Span::styled("● ", Style::default().fg(self.form.priority.color(&self.config.theme))),
+            Span::raw(self.form.priority.label()),
+            Span::raw(" ▶"),
+        ]))
+        .block(Block::bordered().title("Priority (←/→)").style(priority_style))
+        .style(priority_style);
+        let [priority_area, recurrence_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(form_chunks[3]);
+        frame.render_widget(priority_input, priority_area);
+
+        // Recurrence field
+        let recurrence_style = if self.form.field_index == 4 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        let recurrence_input = Paragraph::new(format!(
+            "◀ {} ▶",
+            Recurrence::label(self.form.recurrence)
+        ))
+        .block(Block::bordered().title("Repeats (←/→)").style(recurrence_style))
+        .style(recurrence_style);
+        frame.render_widget(recurrence_input, recurrence_area);
+
+        // Tags field
+        let tags_style = if self.form.field_index == 5 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        let tags_input = Paragraph::new(text_with_cursor(&self.form.tags, self.form.cursor_in(5)))
+        .block(Block::bordered().title("Tags (comma separated)").style(tags_style))
+        .style(tags_style);
+        frame.render_widget(tags_input, form_chunks[4]);
+
+        // Notes field
+        let notes_style = if self.form.field_index == 6 {
+            Style::default().fg(self.config.theme.accent_fg)
+        } else {
+            Style::default()
+        };
+        let notes_input = Paragraph::new(text_with_cursor(&self.form.notes, self.form.cursor_in(6)))
+        .wrap(Wrap { trim: false })
+        .scroll((cursor_line(&self.form.notes, self.form.cursors[6]).saturating_sub(2), 0))
+        .block(Block::bordered().title("Notes (Enter: new line)").style(notes_style))
+        .style(notes_style);
+        frame.render_widget(notes_input, form_chunks[5]);
+
+        // Validation error and instructions
+        let mut lines = Vec::new();
+        if let Some(error) = &self.form.error {
+            lines.push(Line::from(error.as_str()).style(Style::default().fg(self.config.theme.error_fg)));
+        }
+        lines.push(
+            Line::from("Tab/Shift+Tab: Navigate | Ctrl+S/Enter: Save | Esc: Cancel")
+                .style(Style::default().fg(self.config.theme.hint_fg)),
+        );
+        let instructions = Paragraph::new(lines).alignment(Alignment::Center);
+        frame.render_widget(instructions, form_chunks[6]);
+    }
+
+    /// Month grid for picking the form's date, one 3-column cell per day
+    fn render_date_picker(&self, frame: &mut Frame) {
+        let day = self.calendar_day;
+        let first = day.with_day(1).unwrap_or(day);
+        let days_in_month = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map_or(31, |last| last.day());
+        let offset = first.weekday().num_days_from_monday();
+        let weeks = (offset + days_in_month).div_ceil(7);
+
+        let area = frame.area();
+        // Seven 3-column days, the border and a column of padding on each side
+        let width = (7 * 3 + 4).min(area.width);
+        let height = (weeks as u16 + 1 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.width.saturating_sub(width) / 2,
+            y: area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, popup_area);
+        let block = Block::bordered()
+            .title(first.format("%B %Y").to_string())
+            .title_bottom(Line::from(" [/]: month ").right_aligned())
+            .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background));
+        let inner = block.inner(popup_area).inner(Margin { vertical: 0, horizontal: 1 });
+        frame.render_widget(block, popup_area);
+
+        let rows = Layout::vertical([Constraint::Length(1); 7]).split(inner);
+        let columns = Layout::horizontal([Constraint::Length(3); 7]);
+        for (name, cell) in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]
+            .into_iter()
+            .zip(columns.split(rows[0]).iter())
+        {
+            frame.render_widget(Paragraph::new(name).style(Style::default().add_modifier(Modifier::BOLD)), *cell);
+        }
+
+        let today = Local::now().date_naive();
+        for (week, row) in rows.iter().skip(1).take(weeks as usize).enumerate() {
+            for (weekday, cell) in columns.split(*row).iter().enumerate() {
+                let Some(date) = (week * 7 + weekday + 1)
+                    .checked_sub(offset as usize)
+                    .filter(|n| (1..=days_in_month as usize).contains(n))
+                    .and_then(|n| first.with_day(n as u32))
+                else {
+                    continue;
+                };
+                let mut style = Style::default().fg(self.config.theme.pending_fg);
+                if date == today {
+                    style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+                }
+                if date == day {
+                    style = style.fg(self.config.theme.background).bg(self.config.theme.accent_fg);
+                }
+                frame.render_widget(Paragraph::new(format!("{:>2}", date.day())).style(style), *cell);
+            }
+        }
+    }
+
+    /// Full-screen editor for the selected task's notes
+    fn render_notes_editor(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let title = self
+            .edit_id
+            .and_then(|id| self.items.iter().find(|item| item.id == id))
+            .map_or(String::new(), |item| item.title.clone());
+
+        frame.render_widget(Clear, area);
+        let block = Block::bordered()
+            .title(format!("Notes: {}", title))
+            .title_bottom(Line::from(" Ctrl+S: Save | Esc: Cancel ").right_aligned())
+            .style(Style::default().bg(self.config.theme.background));
+        // Keep the end of the text, where typing happens, in view
+        let inner_height = block.inner(area).height;
+        let mut lines: Vec<Line> = self.notes_input.split('\n').map(Line::from).collect();
+        let line_count = lines.len() as u16;
+        if let Some(last) = lines.last_mut() {
+            last.push_span(Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)));
+        }
+        let editor = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((line_count.saturating_sub(inner_height), 0))
+            .block(block);
+        frame.render_widget(editor, area);
+    }
+
+    fn render_import_popup(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let width = (area.width / 2).max(50).min(area.width);
+        let height = 4.min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let input = Paragraph::new(vec![
+            Line::from(vec![
+                Span::raw(self.import_path.as_str()),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+            Line::from("Enter: Import | Esc: Cancel").style(Style::default().fg(self.config.theme.hint_fg)),
+        ])
+        .block(
+            Block::bordered()
+                .title("Import CSV / Markdown / todo.txt / Taskwarrior")
+                .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)),
+        );
+        frame.render_widget(input, popup_area);
+    }
+
+    fn render_project_switcher(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let width = (area.width / 2).max(40).min(area.width);
+        let height = (self.projects.len() as u16 + 3).min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let mut lines: Vec<Line> = self
+            .projects
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let marker = if *path == self.save_path { "● " } else { "  " };
+                let line = Line::from(format!("{}{}", marker, storage::project_name(path)));
+                if i == self.project_selected {
+                    line.style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        lines.push(Line::from("Enter: Open | Shift+N: New | Esc: Cancel").style(Style::default().fg(self.config.theme.hint_fg)));
+
+        let popup = Paragraph::new(lines).block(
+            Block::bordered()
+                .title("Projects")
+                .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)),
+        );
+        frame.render_widget(popup, popup_area);
+    }
+
+    fn render_new_project_popup(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let width = (area.width / 2).max(40).min(area.width);
+        let height = 4.min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let input = Paragraph::new(vec![
+            Line::from(vec![
+                Span::raw(self.project_name.as_str()),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+            Line::from("Enter: Create | Esc: Back").style(Style::default().fg(self.config.theme.hint_fg)),
+        ])
+        .block(
+            Block::bordered()
+                .title("New project name")
+                .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)),
+        );
+        frame.render_widget(input, popup_area);
+    }
+
+    fn render_add_subtask_popup(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let width = (area.width / 2).max(40).min(area.width);
+        let height = 4.min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let input = Paragraph::new(vec![
+            Line::from(vec![
+                Span::raw(self.subtask_input.as_str()),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+            Line::from("Enter: Add | Esc: Cancel").style(Style::default().fg(self.config.theme.hint_fg)),
+        ])
+        .block(
+            Block::bordered()
+                .title("New subtask")
+                .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)),
+        );
+        frame.render_widget(input, popup_area);
+    }
+
+    fn render_context_menu(&self, frame: &mut Frame) {
+        let lines: Vec<Line> = CONTEXT_MENU_ITEMS
+            .iter()
+            .enumerate()
+            .map(|(i, &item)| {
+                let line = Line::from(format!(" {}", item));
+                if i == self.context_menu_selected {
+                    line.style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        frame.render_widget(Clear, self.context_menu_area);
+        let menu = Paragraph::new(lines)
+            .block(Block::bordered().style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)));
+        frame.render_widget(menu, self.context_menu_area);
+    }
+
+    fn render_conflict_popup(&self, frame: &mut Frame) {
+        let message = format!(
+            "{} was changed by another program since rtodo last read it.\n\n\
+             r: reload from disk (your changes stay undoable)\n\
+             o: overwrite it with your changes\n\
+             c: save your changes to {} and reload\n\
+             Esc: decide later",
+            self.save_path.display(),
+            storage::conflict_path(&self.save_path).display()
+        );
+        let area = frame.area();
+        let width = (area.width * 3 / 4).max(40).min(area.width);
+        let height = 9.min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup_area);
+
+        let popup = Paragraph::new(message)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::bordered()
+                .title("File Changed On Disk")
+                .style(Style::default().fg(self.config.theme.accent_fg).bg(self.config.theme.background)),
+        );
+        frame.render_widget(popup, popup_area);
+    }
+
+    fn render_confirm_delete_popup(&self, frame: &mut Frame) {
+        let message = match self.pending_delete {
+            Some(id) => match self.items.iter().find(|item| item.id == id) {
+                Some(item) => format!("Move '{}' to the trash? (y/n)", item.title),
+                None => "The task was removed in the meantime. (any key)".to_string(),
+            },
+            None => format!("Move {} selected tasks to the trash? (y/n)", self.selected_ids.len()),
+        };
+        render_confirm_popup(frame, "Confirm Delete", &message, &self.config.theme);
+    }
+}
+
+/// Gauge color for a completion ratio between 0 and 1: the theme's low color below a
+/// third, its middle color up to two thirds, its high color above
+fn progress_color(ratio: f64, theme: &Theme) -> Color {
+    if ratio < 1.0 / 3.0 {
+        theme.progress_low_fg
+    } else if ratio <= 2.0 / 3.0 {
+        theme.progress_mid_fg
+    } else {
+        theme.progress_high_fg
+    }
+}
+
+fn tag_color(tag: &str, theme: &Theme) -> Color {
+    let hash = tag
+        .to_lowercase()
+        .bytes()
+        .fold(0usize, |acc, b| acc.wrapping_mul(31).wrapping_add(b as usize));
+    theme.tag_colors[hash % theme.tag_colors.len()]
+}
+
+/// One-line yes/no question centred over everything else
+fn render_confirm_popup(frame: &mut Frame, title: &str, message: &str, theme: &Theme) {
+    let area = frame.area();
+    let longest = message.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let width = (longest as u16 + 4).max(30).min(area.width);
+    let height = (message.lines().count() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: (area.width - width) / 2,
+        y: (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup_area);
+
+    let popup = Paragraph::new(message)
+    .alignment(Alignment::Center)
+    .block(
+        Block::bordered()
+            .title(title)
+            .style(Style::default().fg(theme.error_fg).bg(theme.background)),
+    );
+    frame.render_widget(popup, popup_area);
+}
+
+/// Lists `HELP_SECTIONS` in two columns, split so both hold about as many lines
+fn render_help_popup(frame: &mut Frame, theme: &Theme) {
+    let area = frame.area();
+    let width = (area.width * 9 / 10).max(60).min(area.width);
+    let height = (area.height * 9 / 10).max(20).min(area.height);
+    let popup_area = Rect {
+        x: (area.width - width) / 2,
+        y: (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup_area);
+    let block = Block::bordered()
+        .title("Help")
+        .title_bottom(Line::from(" Press any key to close ").right_aligned())
+        .style(Style::default().fg(theme.accent_fg).bg(theme.background));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let key_width = HELP_SECTIONS
+        .iter()
+        .flat_map(|(_, bindings)| bindings.iter())
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let section_lines = |(title, bindings): &HelpSection| {
+        let mut lines = vec![Line::from(*title).style(Style::default().add_modifier(Modifier::BOLD))];
+        lines.extend(bindings.iter().map(|(key, action)| {
+            Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", key, width = key_width), Style::default().fg(theme.key_fg)),
+                Span::styled(*action, Style::default().fg(theme.hint_fg)),
+            ])
+        }));
+        lines.push(Line::default());
+        lines
+    };
+
+    let total: usize = HELP_SECTIONS.iter().map(|(_, bindings)| bindings.len() + 2).sum();
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for section in HELP_SECTIONS {
+        if left.len() < total / 2 {
+            left.extend(section_lines(section));
+        } else {
+            right.extend(section_lines(section));
+        }
+    }
+
+    let [left_area, right_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(inner);
+    frame.render_widget(Paragraph::new(left), left_area.inner(Margin::new(1, 0)));
+    frame.render_widget(Paragraph::new(right), right_area.inner(Margin::new(1, 0)));
+}
+
+/// `text` with the character at byte offset `cursor` (or a space past the end) shown
+/// reversed as the text cursor
+fn text_with_cursor(text: &str, cursor: Option<usize>) -> Text<'_> {
+    let Some(cursor) = cursor else {
+        return Text::raw(text);
+    };
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text.split('\n') {
+        let end = start + line.len();
+        if (start..=end).contains(&cursor) {
+            let (before, rest) = line.split_at(cursor - start);
+            let mut after = rest.chars();
+            let under = after.next().map_or(" ".to_string(), String::from);
+            lines.push(Line::from(vec![
+                Span::raw(before),
+                Span::styled(under, Style::default().add_modifier(Modifier::REVERSED)),
+                Span::raw(after.as_str()),
+            ]));
+        } else {
+            lines.push(Line::raw(line));
+        }
+        start = end + 1;
+    }
+    Text::from(lines)
+}
+
+/// `3 tasks, 1 skipped from tasks.csv`, followed by why each entry was skipped
+fn import_summary(result: &import::ImportResult, path: &Path) -> String {
+    let mut message = format!(
+        "{} tasks, {} skipped from {}",
+        result.items.len(),
+        result.errors.len(),
+        path.display()
+    );
+    if result.ignored > 0 {
+        message.push_str(&format!(" ({} deleted or waiting tasks left out)", result.ignored));
+    }
+    if !result.errors.is_empty() {
+        message.push('\n');
+        for error in &result.errors {
+            message.push('\n');
+            message.push_str(error);
+        }
+    }
+    message
+}
+
+/// `s` cut to at most `max_chars` characters, ending in `…` when anything was cut
+fn truncate_with_ellipsis(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let mut truncated: String = s.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Line number (from 0) that byte offset `cursor` falls on
+fn cursor_line(text: &str, cursor: usize) -> u16 {
+    text.get(..cursor).unwrap_or(text).matches('\n').count() as u16
+}
+
+/// Moves a list cursor one row down or up, wrapping around at either end
+fn step_selection(state: &mut TableState, count: usize, forward: bool) {
+    if count == 0 {
+        return;
+    }
+    let selected = state.selected().unwrap_or(0);
+    state.select(Some(if forward {
+        (selected + 1) % count
+    } else {
+        (selected + count - 1) % count
+    }));
+}
+
+fn render_notice_popup(frame: &mut Frame, notice: &Notice, theme: &Theme) {
+    let area = frame.area();
+    let width = (area.width * 2 / 3).max(40).min(area.width);
+    let height = (notice.message.lines().count() as u16 + 4).max(9).min(area.height);
+    let popup_area = Rect {
+        x: (area.width - width) / 2,
+        y: (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup_area);
+
+    let popup = Paragraph::new(notice.message.as_str())
+    .wrap(Wrap { trim: false })
+    .block(
+        Block::bordered()
+            .title(notice.title)
+            .title_bottom(Line::from(" Press any key ").right_aligned())
+            .style(Style::default().fg(theme.accent_fg).bg(theme.background)),
+    );
+    frame.render_widget(popup, popup_area);
+}
+
+/// Keys that change the task list, refused while running read-only
+fn modifies_tasks(key: &KeyEvent) -> bool {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return matches!(key.code, KeyCode::Char('z' | 'y' | 'r') | KeyCode::Up | KeyCode::Down);
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        return matches!(key.code, KeyCode::Up | KeyCode::Down);
+    }
+    matches!(
+        key.code,
+        KeyCode::Char(' ' | 'n' | 'N' | 'e' | 'E' | 'd' | 'D' | 'p' | 'I' | 'A' | 'f' | 'F' | 'O' | '+' | '-' | '1'..='9')
+    )
+}
+
+/// Explains who holds the lock on `save_path` and asks what to do, on two lines. `leave`
+/// names the way out: quitting at startup, or staying in the current project.
+fn lock_prompt(save_path: &Path, pid: Option<u32>, running: bool, leave: &str) -> String {
+    let owner = pid.map_or("another rtodo".to_string(), |pid| format!("rtodo (pid {})", pid));
+    let (leave_key, leave_rest) = leave.split_at(1);
+    if running {
+        format!(
+            "{} is already open in {}.\n[r]ead-only, [t]ake over the lock anyway, or [{}]{}?",
+            save_path.display(),
+            owner,
+            leave_key,
+            leave_rest
+        )
+    } else {
+        format!(
+            "{} is locked by {}, which is no longer running (it probably crashed).\n[t]ake over the lock, [r]ead-only, or [{}]{}? [t]",
+            save_path.display(),
+            owner,
+            leave_key,
+            leave_rest
+        )
+    }
+}
+
+/// Locks the todo file against other instances, asking on the terminal what to do when
+/// it's already taken. `None` means the user chose to continue read-only.
+fn acquire_lock(save_path: &Path) -> Result<Option<lock::FileLock>> {
+    let (pid, running) = match lock::acquire(save_path)
+        .wrap_err_with(|| format!("failed to lock {}", save_path.display()))?
+    {
+        lock::LockAttempt::Acquired(file_lock) => return Ok(Some(file_lock)),
+        lock::LockAttempt::Held { pid, running } => (pid, running),
+    };
+
+    print!("{} ", lock_prompt(save_path, pid, running, "quit"));
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "r" => Ok(None),
+        "t" => Ok(Some(lock::reclaim(save_path)?)),
+        "" if !running => Ok(Some(lock::reclaim(save_path)?)),
+        _ => {
+            // Nothing is wrong, so leave without an error report
+            println!("Left {} untouched.", save_path.display());
+            std::process::exit(0);
+        }
+    }
+}
+
+/// Asks for the passphrase of an encrypted todo file until it's right, or for a new one when
+/// `encrypt` is set. Returns the cipher to read the file with and whether to encrypt saves,
+/// or `None` if the prompt was cancelled.
+fn setup_encryption(
+    save_path: &Path,
+    encrypt: bool,
+    decrypt: bool,
+) -> Result<Option<(Option<crypto::Cipher>, bool)>> {
+    let content = fs::read_to_string(save_path).unwrap_or_default();
+    if crypto::is_encrypted(&content) {
+        loop {
+            let Some(passphrase) = prompt_passphrase(&format!("Passphrase for {}: ", save_path.display()))? else {
+                return Ok(None);
+            };
+            let cipher = crypto::Cipher::for_file(&passphrase, &content)
+                .and_then(|cipher| cipher.decrypt(&content).map(|_| cipher));
+            match cipher {
+                Ok(cipher) => return Ok(Some((Some(cipher), !decrypt))),
+                Err(crypto::DecryptError::WrongPassphrase) => println!("Wrong passphrase, try again."),
+                Err(err) => return Err(eyre!("{}: {}", save_path.display(), err)),
+            }
+        }
+    }
+    if !encrypt {
+        return Ok(Some((None, false)));
+    }
+    loop {
+        let Some(passphrase) = prompt_passphrase("New passphrase: ")? else {
+            return Ok(None);
+        };
+        if passphrase.is_empty() {
+            println!("The passphrase can't be empty.");
+            continue;
+        }
+        if prompt_passphrase("Repeat passphrase: ")?.as_deref() != Some(passphrase.as_str()) {
+            println!("The passphrases don't match, try again.");
+            continue;
+        }
+        let cipher = crypto::Cipher::new(&passphrase).map_err(|err| eyre!(err))?;
+        return Ok(Some((Some(cipher), true)));
+    }
+}
+
+/// Reads a line from the terminal, echoing `*` for each character. `None` if cancelled with
+/// Esc or Ctrl+C.
+fn prompt_passphrase(prompt: &str) -> Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    terminal::enable_raw_mode()?;
+    let result = read_masked_line();
+    terminal::disable_raw_mode()?;
+    println!();
+    result
+}
+
+fn read_masked_line() -> Result<Option<String>> {
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(line)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Backspace if line.pop().is_some() => print!("\x08 \x08"),
+            KeyCode::Char(c) => {
+                line.push(c);
+                print!("*");
+            }
+            _ => {}
+        }
+        io::stdout().flush()?;
+    }
+}
+
+/// Picks where tasks are stored, in order: `--file`, `RTODO_FILE`, `RTODO_PROFILE` (a list
+/// in the data directory, created empty if it doesn't exist yet), then the default. For the
+/// default a `todos.json` in the working directory is still honoured so existing setups keep
+/// working; otherwise the platform data directory is used (e.g. `~/.local/share/rtodo/todos.json`
+/// on Linux).
+fn resolve_save_path(file: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(path) = file {
+        return Ok(path);
+    }
+    if let Some(path) = env::var_os("RTODO_FILE").filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let profile = env::var("RTODO_PROFILE").ok().filter(|name| !name.trim().is_empty());
+
+    let legacy = Path::new(SAVE_FILE);
+    if profile.is_none() && legacy.exists() {
+        return Ok(legacy.to_path_buf());
+    }
+
+    let data_dir = match ProjectDirs::from("", "", "rtodo") {
+        Some(dirs) => dirs.data_dir().to_path_buf(),
+        None if profile.is_some() => PathBuf::new(),
+        None => return Ok(legacy.to_path_buf()),
+    };
+    fs::create_dir_all(&data_dir)?;
+    let Some(profile) = profile else {
+        return Ok(data_dir.join(SAVE_FILE));
+    };
+    // Same rules as projects made in the switcher, since profiles show up there too
+    let name = profile.trim();
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(eyre!("invalid RTODO_PROFILE {:?}", profile));
+    }
+    if name.ends_with(".toml") || name.ends_with(".json") {
+        return Ok(data_dir.join(name));
+    }
+    let toml = data_dir.join(format!("{}.toml", name));
+    Ok(if toml.exists() { toml } else { data_dir.join(format!("{}.json", name)) })
+}
+
+/// Rewrites `input` as `output` without decoding individual tasks, so nothing is lost
+fn convert(input: &Path, output: &Path) -> Result<()> {
+    let content = fs::read_to_string(input)
+        .wrap_err_with(|| format!("failed to read {}", input.display()))?;
+    let state = storage::parse_state(&content, StorageFormat::from_path(input))
+        .map_err(|err| eyre!("failed to parse {}: {err}", input.display()))?;
+    let converted = storage::serialize_state(&state, StorageFormat::from_path(output))
+        .map_err(|err| eyre!("failed to serialize tasks: {err}"))?;
+    storage::write_atomically(output, &converted)
+        .wrap_err_with(|| format!("failed to write {}", output.display()))?;
+    println!(
+        "Converted {} tasks from {} to {}",
+        state.items.len(),
+        input.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let cli = Cli::parse();
+    let (mut export, mut import_from, mut merge_from) = (None, None, None);
+    match cli.command {
+        Some(Command::Convert { input, output }) => return convert(&input, &output),
+        Some(Command::Export { format, output, only }) => export = Some((format, output, only)),
+        Some(Command::Import { path, format, yes }) => import_from = Some((path, format, yes)),
+        Some(Command::Merge { other, yes }) => merge_from = Some((other, yes)),
+        None => {}
+    }
+    let mut save_path = resolve_save_path(cli.file)?;
+    if let Some(format) = cli.format
+        && !save_path.exists()
+    {
+        save_path.set_extension(format.extension());
+    }
+    let mut config = match config::config_path() {
+        Some(path) => config::load(&path)
+            .map_err(|err| eyre!("invalid config file {}: {}", path.display(), err))?,
+        None => Config::default(),
+    };
+    config.theme = match (cli.theme, theme::theme_path()) {
+        (Some(name), _) => name.theme(),
+        (None, Some(path)) => theme::load(&path)
+            .map_err(|err| eyre!("invalid theme file {}: {}", path.display(), err))?,
+        (None, None) => Theme::default(),
+    };
+    if let Some((format, output, only)) = export {
+        // Only reads the file, so it doesn't need the lock (and works while rtodo is open)
+        let Some((cipher, encrypt_on_save)) = setup_encryption(&save_path, false, false)? else {
+            return Ok(());
+        };
+        let app = App::new(save_path, cli.backups, None, cipher, encrypt_on_save, config)?;
+        return app.export_to(format, output.as_deref(), only);
+    }
+    // A missing directory is created now, so a bad path stops here with a readable error
+    // instead of failing every save. Creating the lock file next to the todo file then
+    // proves the directory is writable before any edits are made.
+    if let Some(parent) = save_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("failed to create the directory {}", parent.display()))?;
+    }
+    // Owned by the app from here on; released when it is dropped, including while
+    // unwinding a panic
+    let file_lock = acquire_lock(&save_path)?;
+    // Returning rather than exiting on a cancelled prompt, so the lock is released
+    let Some((cipher, encrypt_on_save)) = setup_encryption(&save_path, cli.encrypt, cli.decrypt)?
+    else {
+        return Ok(());
+    };
+    let mut app = App::new(save_path, cli.backups, file_lock, cipher, encrypt_on_save, config)?;
+    if let Some(enabled) = cli.change_log {
+        app.log_changes = enabled;
+        app.reset_change_log();
+    }
+    if let Some(source) = cli.recover {
+        let source = source
+            .or_else(|| storage::latest_corrupt_backup(&app.save_path))
+            .ok_or_else(|| eyre!("no damaged copy of {} to recover from", app.save_path.display()))?;
+        app.recover_from(&source)?;
+    }
+    if let Some((other, yes)) = merge_from {
+        return app.merge_file(&other, yes);
+    }
+    if let Some((path, format, yes)) = import_from {
+        return app.import_file(&path, format, yes);
+    }
+    if let Some(compact) = cli.compact_json {
+        app.compact_json = compact;
+    }
+    if cli.encrypt || cli.decrypt || cli.change_log.is_some() || cli.compact_json.is_some() {
+        // Rewrite the file in its new form, or with the new setting, right away
+        app.mark_dirty();
+        app.flush();
+    }
+    let terminal = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture, EnableBracketedPaste)?;
+    let app_result = app.run(terminal);
+    // Best effort, like `ratatui::restore`: the terminal is being torn down either way
+    let _ = execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+    ratatui::restore();
+    app_result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use chrono::NaiveTime;
+
+    /// Opens `path` for writing, holding its lock
+    fn open(path: &Path) -> App {
+        let Ok(lock::LockAttempt::Acquired(file_lock)) = lock::acquire(path) else {
+            panic!("{} is locked", path.display());
+        };
+        App::new(path.to_path_buf(), 5, Some(file_lock), None, false, Config::default())
+            .expect("open list")
+    }
+
+    /// An app on a fresh list in `dir` holding tasks with the given titles, ids from 1
+    fn app_with_tasks(dir: &TempDir, titles: &[&str]) -> App {
+        let mut app = open(&dir.join("todos.json"));
+        for title in titles {
+            let due = Local::now().date_naive().and_time(date_utils::END_OF_DAY);
+            app.items.push(TodoItem::new(app.next_id, title.to_string(), String::new(), due));
+            app.next_id += 1;
+        }
+        app
+    }
+
+    #[test]
+    fn malformed_save_file_is_backed_up_byte_for_byte() {
+        let dir = TempDir::new("malformed-json");
+        let path = dir.join("todos.json");
+        let content = b"{\"version\": 3, \"items\": [{\"id\": 1, \"title\": \"half";
+        fs::write(&path, content).unwrap();
+
+        let app = open(&path);
+
+        assert!(app.items.is_empty());
+        let backup = storage::latest_corrupt_backup(&path).expect("backup was made");
+        assert_eq!(fs::read(&backup).unwrap(), content);
+        assert_eq!(fs::read(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn save_file_that_is_not_utf8_is_backed_up_and_not_overwritten_blindly() {
+        let dir = TempDir::new("not-utf8");
+        let path = dir.join("todos.json");
+        let content = [0xff, 0xfe, b'[', 0x80, b']'];
+        fs::write(&path, content).unwrap();
+
+        let app = open(&path);
+
+        let backup = storage::latest_corrupt_backup(&path).expect("backup was made");
+        assert_eq!(fs::read(&backup).unwrap(), content);
+        // The file we loaded may be replaced; one changed behind our back may not
+        assert_eq!(app.disk_hash.get(), Some(storage::content_hash(content)));
+    }
+
+    #[test]
+    fn unreadable_save_file_is_reported_instead_of_starting_empty() {
+        let dir = TempDir::new("unreadable");
+        // Exists, but reading it fails
+        let path = dir.join("todos.json");
+        fs::create_dir(&path).unwrap();
+
+        assert!(App::new(path, 5, None, None, false, Config::default()).is_err());
+    }
+
+    #[test]
+    fn bulk_toggle_completes_marked_tasks_and_clears_the_marks() {
+        let dir = TempDir::new("bulk-toggle");
+        let mut app = app_with_tasks(&dir, &["one", "two", "three"]);
+        app.selected_ids.extend([1, 3]);
+
+        app.toggle_completed();
+
+        let completed: Vec<usize> =
+            app.items.iter().filter(|item| item.completed).map(|item| item.id).collect();
+        assert_eq!(completed, [1, 3]);
+        assert!(app.selected_ids.is_empty());
+    }
+
+    #[test]
+    fn archiving_a_completed_task_hides_it_until_restored() {
+        let dir = TempDir::new("archive-flag");
+        let mut app = app_with_tasks(&dir, &["done", "open"]);
+        app.items[0].completed = true;
+        app.state.select(Some(0));
+
+        app.archive_selected();
+
+        assert!(app.items[0].archived);
+        assert_eq!(app.visible_indices(), [1]);
+        assert_eq!(app.get_progress(), (0, 1));
+
+        app.open_archive_view();
+        app.unarchive_selected();
+
+        assert!(!app.items[0].archived);
+        assert_eq!(app.visible_indices(), [0, 1]);
+    }
+
+    #[test]
+    fn pending_tasks_are_not_archived() {
+        let dir = TempDir::new("archive-pending");
+        let mut app = app_with_tasks(&dir, &["open"]);
+        app.state.select(Some(0));
+
+        app.archive_selected();
+
+        assert!(!app.items[0].archived);
+    }
+
+    #[test]
+    fn archived_flag_is_only_written_when_set() {
+        let due = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_time(date_utils::END_OF_DAY);
+        let mut item = TodoItem::new(1, "task".to_string(), String::new(), due);
+        assert!(serde_json::to_value(&item).unwrap().get("archived").is_none());
+
+        item.archived = true;
+        let value = serde_json::to_value(&item).unwrap();
+        assert_eq!(value["archived"], true);
+        assert!(serde_json::from_value::<TodoItem>(value).unwrap().archived);
+    }
+
+    #[test]
+    fn stats_of_an_empty_list_do_not_divide_by_zero() {
+        let dir = TempDir::new("stats-empty");
+        let app = app_with_tasks(&dir, &[]);
+
+        let stats = app.compute_stats();
+
+        assert_eq!((stats.total, stats.completed, stats.overdue), (0, 0, 0));
+        assert_eq!(stats.completion_rate, 0.0);
+        assert_eq!(stats.avg_days_to_complete, None);
+    }
+
+    #[test]
+    fn stats_count_completed_overdue_and_upcoming_tasks() {
+        let dir = TempDir::new("stats");
+        let mut app = app_with_tasks(&dir, &["done", "late", "soon", "later"]);
+        let today = Local::now().date_naive();
+        app.items[0].completed = true;
+        app.items[0].created_date = today - Days::new(4);
+        app.items[0].completed_date = Some(today);
+        app.items[1].target_datetime = (today - Days::new(2)).and_time(date_utils::END_OF_DAY);
+        app.items[2].target_datetime = (today + Days::new(3)).and_time(date_utils::END_OF_DAY);
+        app.items[3].target_datetime = (today + Days::new(30)).and_time(date_utils::END_OF_DAY);
+
+        let stats = app.compute_stats();
+
+        assert_eq!((stats.total, stats.completed, stats.overdue), (4, 1, 1));
+        assert_eq!(stats.due_this_week, 1);
+        assert_eq!(stats.completion_rate, 25.0);
+        assert_eq!(stats.avg_days_to_complete, Some(4.0));
+    }
+
+    /// Ids of the tasks in a save or archive file on disk
+    fn ids_on_disk(path: &Path) -> Vec<u64> {
+        let content = fs::read_to_string(path).unwrap();
+        let state = storage::parse_state(&content, StorageFormat::Json)
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        state.items.iter().filter_map(|item| item["id"].as_u64()).collect()
+    }
+
+    #[test]
+    fn archiving_completed_tasks_saves_both_files_at_once() {
+        let dir = TempDir::new("archive-completed");
+        let mut app = app_with_tasks(&dir, &["done", "open"]);
+        app.items[0].completed = true;
+        app.flush();
+
+        app.archive_completed();
+
+        assert!(!app.dirty);
+        assert_eq!(ids_on_disk(&app.save_path), [2]);
+        assert_eq!(ids_on_disk(&storage::archive_path(&app.save_path)), [1]);
+    }
+
+    #[test]
+    fn archiving_is_rolled_back_when_the_list_cannot_be_saved() {
+        let dir = TempDir::new("archive-conflict");
+        let mut app = app_with_tasks(&dir, &["done", "open"]);
+        app.items[0].completed = true;
+        app.flush();
+        // Another program rewrote the list, so saving it stops with a conflict
+        fs::write(&app.save_path, "[]").unwrap();
+
+        app.archive_completed();
+
+        assert!(app.save_conflict);
+        assert_eq!(app.items.len(), 2);
+        assert!(ids_on_disk(&storage::archive_path(&app.save_path)).is_empty());
+    }
+
+    #[test]
+    fn restoring_from_the_archive_file_saves_both_files_at_once() {
+        let dir = TempDir::new("unarchive");
+        let mut app = app_with_tasks(&dir, &["done", "open"]);
+        app.items[0].completed = true;
+        app.flush();
+        app.archive_completed();
+
+        app.open_archive_view();
+        app.unarchive_selected();
+
+        assert!(!app.dirty);
+        assert_eq!(ids_on_disk(&app.save_path), [1, 2]);
+        assert!(ids_on_disk(&storage::archive_path(&app.save_path)).is_empty());
+    }
+
+    #[test]
+    fn identical_saves_do_not_use_up_a_backup_slot() {
+        let dir = TempDir::new("identical-saves");
+        let mut app = app_with_tasks(&dir, &["one"]);
+        app.save_tasks().unwrap();
+        app.items[0].title = "changed".to_string();
+        app.save_tasks().unwrap();
+        let newest = fs::read(storage::backup_path(&app.save_path, 1)).unwrap();
+
+        app.save_tasks().unwrap();
+        app.save_tasks().unwrap();
+
+        assert_eq!(fs::read(storage::backup_path(&app.save_path, 1)).unwrap(), newest);
+        assert!(!storage::backup_path(&app.save_path, 2).exists());
+    }
+
+    #[test]
+    fn sort_mode_does_not_change_the_order_tasks_are_saved_in() {
+        let dir = TempDir::new("sort-order");
+        let mut app = app_with_tasks(&dir, &["c", "a", "b"]);
+        let today = Local::now().date_naive();
+        for (item, days) in app.items.iter_mut().zip([5, 1, 3]) {
+            item.target_datetime = (today + Days::new(days)).and_time(date_utils::END_OF_DAY);
+        }
+        app.save_tasks().unwrap();
+        let by_created = fs::read(&app.save_path).unwrap();
+        let items_on_disk = |app: &App| {
+            let content = fs::read_to_string(&app.save_path).unwrap();
+            serde_json::from_str::<serde_json::Value>(&content).unwrap()["items"].to_string()
+        };
+        let items_by_created = items_on_disk(&app);
+
+        app.set_sort_mode(SortMode::TargetDate);
+        let displayed: Vec<usize> = app.items.iter().map(|item| item.id).collect();
+        assert_eq!(displayed, [2, 3, 1]);
+        app.save_tasks().unwrap();
+        assert_eq!(items_on_disk(&app), items_by_created);
+
+        app.set_sort_mode(SortMode::CreatedDate);
+        app.save_tasks().unwrap();
+        assert_eq!(fs::read(&app.save_path).unwrap(), by_created);
+    }
+
+    #[test]
+    fn days_overdue_counts_whole_days_past_the_target_date() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let due = |day: u32, time: NaiveTime| {
+            let target = NaiveDate::from_ymd_opt(2025, 3, day).unwrap().and_time(time);
+            TodoItem::new(1, "Task".to_string(), String::new(), target)
+        };
+
+        assert_eq!(due(11, date_utils::END_OF_DAY).days_overdue(today), None);
+        assert_eq!(due(10, NaiveTime::MIN).days_overdue(today), None);
+        assert_eq!(due(9, date_utils::END_OF_DAY).days_overdue(today), Some(1));
+        assert_eq!(due(2, NaiveTime::MIN).days_overdue(today), Some(8));
+
+        let mut completed = due(2, NaiveTime::MIN);
+        completed.completed = true;
+        assert_eq!(completed.days_overdue(today), None);
+    }
+
+    #[test]
+    fn progress_color_steps_at_a_third_and_two_thirds() {
+        let theme = Theme::default();
+        let color = |completed: u32, total: u32| {
+            progress_color(f64::from(completed) / f64::from(total), &theme)
+        };
+
+        assert_eq!(color(0, 3), theme.progress_low_fg);
+        assert_eq!(color(32, 100), theme.progress_low_fg);
+        assert_eq!(color(1, 3), theme.progress_mid_fg);
+        assert_eq!(color(2, 6), theme.progress_mid_fg);
+        assert_eq!(color(1, 2), theme.progress_mid_fg);
+        assert_eq!(color(2, 3), theme.progress_mid_fg);
+        assert_eq!(color(4, 6), theme.progress_mid_fg);
+        assert_eq!(color(67, 100), theme.progress_high_fg);
+        assert_eq!(color(3, 3), theme.progress_high_fg);
+    }
+
+    #[test]
+    fn progress_color_just_either_side_of_the_boundaries() {
+        let theme = Theme::default();
+        let third = 1.0 / 3.0;
+        let two_thirds = 2.0 / 3.0;
+
+        assert_eq!(progress_color(third - f64::EPSILON, &theme), theme.progress_low_fg);
+        assert_eq!(progress_color(third, &theme), theme.progress_mid_fg);
+        assert_eq!(progress_color(two_thirds, &theme), theme.progress_mid_fg);
+        assert_eq!(progress_color(two_thirds + f64::EPSILON, &theme), theme.progress_high_fg);
+    }
+
+    #[test]
+    fn switching_to_encryption_leaves_no_plaintext_copy() {
+        let dir = TempDir::new("encrypt-switch");
+        let mut app = app_with_tasks(&dir, &["Call Globex about the invoice"]);
+        app.report_save(app.save_tasks());
+        app.items[0].description = "Net 30".to_string();
+        app.report_save(app.save_tasks());
+        let item = serde_json::to_value(&app.items[0]).unwrap();
+        app.write_archive(vec![item]).unwrap();
+        let path = app.save_path.clone();
+        drop(app);
+        fs::copy(&path, storage::corrupt_backup_path(&path)).unwrap();
+        fs::write(changelog::log_path(&path), "Call Globex about the invoice\n").unwrap();
+
+        let Ok(lock::LockAttempt::Acquired(file_lock)) = lock::acquire(&path) else {
+            panic!("{} is locked", path.display());
+        };
+        let cipher = crypto::Cipher::new("correct horse").unwrap();
+        let mut app =
+            App::new(path.clone(), 5, Some(file_lock), Some(cipher), true, Config::default())
+                .expect("open list");
+        app.report_save(app.save_tasks());
+
+        assert!(!app.dirty);
+        for entry in fs::read_dir(path.parent().unwrap()).unwrap() {
+            let copy = entry.unwrap().path();
+            let content = fs::read(&copy).unwrap();
+            assert!(
+                !String::from_utf8_lossy(&content).contains("Globex"),
+                "{} is readable",
+                copy.display()
+            );
+        }
+        assert!(!changelog::log_path(&path).exists());
+        let backup = fs::read_to_string(storage::backup_path(&path, 1)).unwrap();
+        assert!(app.decode(backup).unwrap().contains("Net 30"));
+        assert_eq!(app.read_archive().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn quitting_stays_open_when_the_save_fails() {
+        let dir = TempDir::new("quit-unsaved");
+        let mut app = app_with_tasks(&dir, &["Water plants"]);
+        assert!(app.quit(false));
+
+        app.items[0].title = "Water the plants".to_string();
+        app.mark_dirty();
+        // A directory where the file should be makes every save fail
+        fs::remove_file(&app.save_path).unwrap();
+        fs::create_dir(&app.save_path).unwrap();
+
+        assert!(!app.quit(false));
+        assert!(app.dirty);
+        assert!(app.quit_unsaved);
+        assert!(
+            matches!(&app.status, Some(Status::Error(message)) if message.contains("q again")),
+            "the error is shown"
+        );
+        assert!(app.quit(true));
+    }
+
+    #[test]
+    fn delete_confirmation_deletes_the_task_it_named() {
+        let dir = TempDir::new("pending-delete");
+        let mut app = app_with_tasks(&dir, &["Keep me", "Delete me"]);
+        app.select_task(2);
+        app.start_delete();
+        assert!(app.mode == AppMode::ConfirmDelete);
+
+        // A reload while the popup is open moves the cursor to another task
+        app.select_task(1);
+        app.delete_pending();
+
+        let titles: Vec<&str> = app.items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["Keep me"]);
+        assert_eq!(app.trash.len(), 1);
+        assert_eq!(app.trash[0].task.title, "Delete me");
+        assert_eq!(app.pending_delete, None);
+    }
+
+    #[test]
+    fn delete_confirmation_for_a_task_removed_meanwhile_deletes_nothing() {
+        let dir = TempDir::new("pending-delete-gone");
+        let mut app = app_with_tasks(&dir, &["Keep me", "Delete me"]);
+        app.select_task(2);
+        app.start_delete();
+        app.items.retain(|item| item.id != 2);
+        app.select_task(1);
+
+        app.delete_pending();
+
+        assert_eq!(app.items.len(), 1);
+        assert!(app.trash.is_empty());
+    }
+
+    #[test]
+    fn completing_a_recurring_task_twice_creates_one_follow_up() {
+        let dir = TempDir::new("recur-toggle");
+        let mut app = app_with_tasks(&dir, &["Water plants"]);
+        app.items[0].recurrence = Some(Recurrence::Weekly);
+        let due = app.items[0].target_datetime;
+
+        app.toggle_item(0);
+        app.toggle_item(0);
+        app.toggle_item(0);
+
+        assert!(app.items[0].completed);
+        assert_eq!(app.items.len(), 2);
+        assert_eq!(app.items[0].next_occurrence_id, Some(app.items[1].id));
+        assert!(!app.items[1].completed);
+        assert_eq!(app.items[1].target_datetime, due + Days::new(7));
+        assert_eq!(app.items[1].next_occurrence_id, None);
+    }
+
+    #[test]
+    fn completing_again_after_the_follow_up_is_deleted_creates_a_new_one() {
+        let dir = TempDir::new("recur-toggle-deleted");
+        let mut app = app_with_tasks(&dir, &["Water plants"]);
+        app.items[0].recurrence = Some(Recurrence::Weekly);
+
+        app.toggle_item(0);
+        app.toggle_item(0);
+        app.items.truncate(1);
+        app.toggle_item(0);
+
+        assert_eq!(app.items.len(), 2);
+        assert_eq!(app.items[0].next_occurrence_id, Some(app.items[1].id));
+    }
+
+    #[test]
+    fn merge_leaves_tasks_deleted_here_in_the_trash() {
+        let dir = TempDir::new("merge-trash");
+        let mut app = app_with_tasks(&dir, &["Buy milk", "Call mom"]);
+        app.report_save(app.save_tasks());
+        let other = dir.join("laptop.json");
+        fs::copy(&app.save_path, &other).unwrap();
+        app.select_task(2);
+        app.start_delete();
+        app.delete_pending();
+        app.report_save(app.save_tasks());
+
+        app.merge_file(&other, true).unwrap();
+
+        assert_eq!(ids_on_disk(&app.save_path), [1]);
+        assert_eq!(app.trash.len(), 1);
+    }
+}
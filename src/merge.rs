@@ -0,0 +1,229 @@
+//! Combining a diverged copy of a todo list into the current one.
+
+use chrono::{NaiveDateTime, NaiveTime};
+
+use crate::TodoItem;
+
+/// What a merge did, or would do, to the local list
+#[derive(Default)]
+pub struct MergeReport {
+    // Tasks that only existed in the other copy
+    pub added: Vec<String>,
+    // Tasks where the other copy's version was newer and replaced the local one
+    pub updated: Vec<String>,
+    // Tasks that differ but whose local version is newer, so it was kept
+    pub kept: Vec<String>,
+    // Tasks that differ with no way to tell which is newer; the local version was kept
+    pub conflicts: Vec<String>,
+    // Tasks deleted locally and still in the trash, so they weren't brought back
+    pub trashed: Vec<String>,
+}
+
+impl MergeReport {
+    /// `12 added, 3 updated, 1 kept, 2 conflicts, 1 in the trash`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} updated, {} kept, {} conflicts, {} in the trash",
+            self.added.len(),
+            self.updated.len(),
+            self.kept.len(),
+            self.conflicts.len(),
+            self.trashed.len()
+        )
+    }
+}
+
+/// Merges `other` into `local`. A task in `other` is the same as a local one when both have
+/// the same id and creation date, or else the same title and creation date (ids assigned
+/// separately on two machines can collide). Tasks only in `other` are added, renumbered from
+/// `next_id` if their id is taken, unless they match a task in `trash` (deleted locally).
+/// For tasks in both, the one modified last wins.
+pub fn merge(
+    local: &mut Vec<TodoItem>,
+    trash: &[&TodoItem],
+    other: Vec<TodoItem>,
+    next_id: &mut usize,
+) -> MergeReport {
+    let mut report = MergeReport::default();
+    for mut theirs in other {
+        let Some(index) = find_same(local.iter(), &theirs) else {
+            if find_same(trash.iter().copied(), &theirs).is_some() {
+                report.trashed.push(theirs.title.clone());
+                continue;
+            }
+            if local.iter().any(|ours| ours.id == theirs.id) {
+                theirs.id = *next_id;
+            }
+            *next_id = (*next_id).max(theirs.id + 1);
+            report.added.push(theirs.title.clone());
+            local.push(theirs);
+            continue;
+        };
+
+        let ours = &mut local[index];
+        theirs.id = ours.id;
+        if same_content(ours, &theirs) {
+            continue;
+        }
+        match modified_at(&theirs).cmp(&modified_at(ours)) {
+            std::cmp::Ordering::Greater => {
+                report.updated.push(theirs.title.clone());
+                *ours = theirs;
+            }
+            std::cmp::Ordering::Less => report.kept.push(ours.title.clone()),
+            std::cmp::Ordering::Equal => report.conflicts.push(ours.title.clone()),
+        }
+    }
+    report
+}
+
+/// Position of the task in `items` that is the same as `theirs`, by id or else by title,
+/// each together with the creation date
+fn find_same<'a>(
+    items: impl Iterator<Item = &'a TodoItem> + Clone,
+    theirs: &TodoItem,
+) -> Option<usize> {
+    items
+        .clone()
+        .position(|ours| ours.id == theirs.id && ours.created_date == theirs.created_date)
+        .or_else(|| {
+            items.clone().position(|ours| {
+                ours.title == theirs.title && ours.created_date == theirs.created_date
+            })
+        })
+}
+
+/// When the task last changed; tasks never changed since being created count from then
+fn modified_at(item: &TodoItem) -> NaiveDateTime {
+    item.modified
+        .unwrap_or_else(|| item.created_date.and_time(NaiveTime::MIN))
+}
+
+fn same_content(a: &TodoItem, b: &TodoItem) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    /// A task created on January `created`, last modified at `modified` o'clock that day
+    fn task(id: usize, title: &str, created: u32, modified: Option<u32>) -> TodoItem {
+        let due = date(28).and_time(NaiveTime::MIN);
+        let mut item = TodoItem::new(id, title.to_string(), String::new(), due);
+        item.created_date = date(created);
+        item.modified = modified.map(|hour| date(created).and_hms_opt(hour, 0, 0).unwrap());
+        item
+    }
+
+    fn ids_and_titles(items: &[TodoItem]) -> Vec<(usize, &str)> {
+        items.iter().map(|item| (item.id, item.title.as_str())).collect()
+    }
+
+    #[test]
+    fn new_task_keeps_a_free_id() {
+        let mut local = vec![task(1, "Buy milk", 1, None)];
+        let mut next_id = 2;
+
+        let report = merge(&mut local, &[], vec![task(7, "Call mom", 2, None)], &mut next_id);
+
+        assert_eq!(report.added, ["Call mom"]);
+        assert_eq!(ids_and_titles(&local), [(1, "Buy milk"), (7, "Call mom")]);
+        assert_eq!(next_id, 8);
+    }
+
+    #[test]
+    fn id_collision_with_another_task_is_renumbered() {
+        let mut local = vec![task(1, "Buy milk", 1, None), task(2, "Pay rent", 1, None)];
+        let mut next_id = 3;
+        let other = vec![task(1, "Call mom", 2, None), task(2, "Walk dog", 3, None)];
+
+        let report = merge(&mut local, &[], other, &mut next_id);
+
+        assert_eq!(report.added, ["Call mom", "Walk dog"]);
+        assert_eq!(
+            ids_and_titles(&local),
+            [(1, "Buy milk"), (2, "Pay rent"), (3, "Call mom"), (4, "Walk dog")]
+        );
+        assert_eq!(next_id, 5);
+    }
+
+    #[test]
+    fn same_title_and_creation_date_under_another_id_is_the_same_task() {
+        let mut local = vec![task(1, "Buy milk", 1, Some(9))];
+        let mut theirs = task(4, "Buy milk", 1, Some(10));
+        theirs.description = "Oat milk".to_string();
+        let mut next_id = 2;
+
+        let report = merge(&mut local, &[], vec![theirs], &mut next_id);
+
+        assert_eq!(report.updated, ["Buy milk"]);
+        assert!(report.added.is_empty());
+        assert_eq!(ids_and_titles(&local), [(1, "Buy milk")]);
+        assert_eq!(local[0].description, "Oat milk");
+        assert_eq!(next_id, 2);
+    }
+
+    #[test]
+    fn their_newer_version_replaces_ours() {
+        let mut local = vec![task(1, "Buy milk", 1, Some(9))];
+        let theirs = task(1, "Buy oat milk", 1, Some(10));
+
+        let report = merge(&mut local, &[], vec![theirs], &mut 2);
+
+        assert_eq!(report.updated, ["Buy oat milk"]);
+        assert_eq!(ids_and_titles(&local), [(1, "Buy oat milk")]);
+    }
+
+    #[test]
+    fn our_newer_version_is_kept() {
+        let mut local = vec![task(1, "Buy oat milk", 1, Some(10))];
+        let theirs = task(1, "Buy milk", 1, Some(9));
+
+        let report = merge(&mut local, &[], vec![theirs], &mut 2);
+
+        assert_eq!(report.kept, ["Buy oat milk"]);
+        assert!(report.updated.is_empty());
+        assert_eq!(ids_and_titles(&local), [(1, "Buy oat milk")]);
+    }
+
+    #[test]
+    fn equal_timestamps_are_a_conflict() {
+        let mut local = vec![task(1, "Buy oat milk", 1, Some(9))];
+        let theirs = task(1, "Buy milk", 1, Some(9));
+
+        let report = merge(&mut local, &[], vec![theirs], &mut 2);
+
+        assert_eq!(report.conflicts, ["Buy oat milk"]);
+        assert!(report.updated.is_empty() && report.kept.is_empty());
+        assert_eq!(ids_and_titles(&local), [(1, "Buy oat milk")]);
+    }
+
+    #[test]
+    fn identical_task_is_not_reported() {
+        let mut local = vec![task(1, "Buy milk", 1, Some(9))];
+
+        let report = merge(&mut local, &[], vec![task(1, "Buy milk", 1, Some(9))], &mut 2);
+
+        assert_eq!(report.summary(), "0 added, 0 updated, 0 kept, 0 conflicts, 0 in the trash");
+    }
+
+    #[test]
+    fn task_deleted_locally_is_not_added_back() {
+        let mut local = vec![task(1, "Buy milk", 1, None)];
+        let deleted = task(2, "Call mom", 1, None);
+        let mut next_id = 3;
+        let other = vec![task(2, "Call mom", 1, None), task(3, "Pay rent", 1, None)];
+
+        let report = merge(&mut local, &[&deleted], other, &mut next_id);
+
+        assert_eq!(report.trashed, ["Call mom"]);
+        assert_eq!(report.added, ["Pay rent"]);
+        assert_eq!(ids_and_titles(&local), [(1, "Buy milk"), (3, "Pay rent")]);
+    }
+}
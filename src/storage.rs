@@ -0,0 +1,549 @@
+//! Reading and writing the todo file.
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{ColumnWidths, SortMode};
+use crate::date_utils::END_OF_DAY;
+
+/// Schema version written to new save files. Bump it together with a new entry in
+/// `MIGRATIONS` whenever the layout changes in a way older files can't just deserialize.
+pub const CURRENT_VERSION: u64 = 3;
+
+/// Upgrades for each historical schema, `MIGRATIONS[n - 1]` turning version `n` into `n + 1`
+const MIGRATIONS: [fn(Value) -> Value; (CURRENT_VERSION - 1) as usize] =
+    [migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// On-disk layout of the save file (schema version 3). Tasks are kept as raw JSON values
+/// here and decoded one by one, so a single malformed entry doesn't take the whole list down.
+#[derive(Serialize, Deserialize)]
+pub struct AppState {
+    pub version: u64,
+    #[serde(flatten)]
+    pub settings: Settings,
+    // Lowest id not handed out yet. Kept in the file so ids of archived tasks aren't reused;
+    // 0 in files from before it was added.
+    #[serde(default)]
+    pub next_id: usize,
+    pub items: Vec<Value>,
+    // Deleted tasks, restorable from the trash view until they are purged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trash: Vec<Value>,
+}
+
+/// View preferences restored on the next launch
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub sort_mode: SortMode,
+    // false when the user flipped the sort mode's natural direction with R
+    pub sort_ascending: bool,
+    pub column_widths: ColumnWidths,
+    // Append task changes to `<name>.log.jsonl`
+    pub change_log: bool,
+    // One-line JSON instead of pretty-printed; ignored for TOML files
+    pub compact_json: bool,
+    // Index into the sorted task list of the row selected at exit
+    pub selected: Option<usize>,
+    // Task ids from top to bottom while sorting manually
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub manual_order: Vec<usize>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sort_mode: SortMode::default(),
+            sort_ascending: true,
+            column_widths: ColumnWidths::default(),
+            change_log: false,
+            compact_json: false,
+            selected: None,
+            manual_order: Vec::new(),
+        }
+    }
+}
+
+/// Serialization used for a todo file, picked from its extension
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum StorageFormat {
+    /// Pretty-printed JSON (the default)
+    Json,
+    /// Settings as top-level keys followed by one `[[task]]` table per task
+    Toml,
+}
+
+impl StorageFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => StorageFormat::Toml,
+            _ => StorageFormat::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            StorageFormat::Json => "json",
+            StorageFormat::Toml => "toml",
+        }
+    }
+}
+
+pub enum ParseError {
+    /// The file isn't a todo file at all, or is damaged
+    Invalid(String),
+    /// Written by a newer rtodo; loading it would drop whatever that version added
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Invalid(err) => f.write_str(err),
+            ParseError::UnsupportedVersion(version) => write!(
+                f,
+                "schema version {} is newer than this rtodo supports ({})",
+                version, CURRENT_VERSION
+            ),
+        }
+    }
+}
+
+/// Name of the task array in TOML files; `[[task]]` reads better than `[[items]]`
+const TOML_TASKS_KEY: &str = "task";
+
+pub fn parse_state(content: &str, format: StorageFormat) -> Result<AppState, ParseError> {
+    let invalid = |err: &dyn fmt::Display| ParseError::Invalid(err.to_string());
+    let mut value: Value = match format {
+        StorageFormat::Json => serde_json::from_str(content).map_err(|err| invalid(&err))?,
+        StorageFormat::Toml => toml::from_str(content).map_err(|err| invalid(&err))?,
+    };
+    if format == StorageFormat::Toml
+        && let Some(object) = value.as_object_mut()
+    {
+        let tasks = object
+            .remove(TOML_TASKS_KEY)
+            .unwrap_or(Value::Array(Vec::new()));
+        object.insert("items".to_string(), tasks);
+    }
+    let value = migrate(value)?;
+    serde_json::from_value(value).map_err(|err| invalid(&err))
+}
+
+/// Best-effort read of a damaged JSON todo file, e.g. one cut off mid-write. Every task
+/// object that is still complete is kept, up to the first point the JSON stops making sense;
+/// settings and the trash are not recovered. Also returns whether anything was left unread.
+pub fn salvage_state(content: &str) -> Result<(AppState, bool), ParseError> {
+    let bare = content.trim_start().starts_with('[');
+    let items_key = if bare { Some(0) } else { content.find("\"items\"") };
+    let start = items_key
+        .and_then(|key| content[key..].find('[').map(|offset| key + offset + 1))
+        .ok_or_else(|| ParseError::Invalid("no task list found".to_string()))?;
+
+    let mut items = Vec::new();
+    let mut rest = &content[start..];
+    let complete = loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.starts_with(']') {
+            break true;
+        }
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(item)) => {
+                items.push(item);
+                rest = &rest[stream.byte_offset()..];
+            }
+            _ => break false,
+        }
+    };
+
+    let value = if bare {
+        Value::Array(items)
+    } else {
+        // The version key is written before the tasks, so it survives truncation
+        let version = content[..start]
+            .find("\"version\"")
+            .map(|key| &content[key + "\"version\"".len()..start])
+            .and_then(|after| after.trim_start().strip_prefix(':'))
+            .map(|after| after.trim_start())
+            .map(|after| &after[..after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len())])
+            .and_then(|digits| digits.parse::<u64>().ok());
+        let mut object = serde_json::Map::new();
+        if let Some(version) = version {
+            object.insert("version".to_string(), Value::from(version));
+        }
+        object.insert("items".to_string(), Value::Array(items));
+        Value::Object(object)
+    };
+    let state = serde_json::from_value(migrate(value)?)
+        .map_err(|err| ParseError::Invalid(err.to_string()))?;
+    Ok((state, complete))
+}
+
+/// Brings a save file of any known schema version up to `CURRENT_VERSION`, one step at a time
+fn migrate(mut value: Value) -> Result<Value, ParseError> {
+    let version = match &value {
+        // Version 1 was a bare array of tasks
+        Value::Array(_) => 1,
+        // Version 2 files from before the version key existed
+        Value::Object(object) => match object.get("version") {
+            None => 2,
+            Some(version) => version
+                .as_u64()
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| {
+                    ParseError::Invalid(format!("invalid schema version {}", version))
+                })?,
+        },
+        _ => return Err(ParseError::Invalid("expected a list of tasks".to_string())),
+    };
+    if version > CURRENT_VERSION {
+        return Err(ParseError::UnsupportedVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        value = migration(value);
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(CURRENT_VERSION));
+    }
+    Ok(value)
+}
+
+/// Wraps the bare task array in the settings envelope
+fn migrate_v1_to_v2(tasks: Value) -> Value {
+    serde_json::json!({ "items": tasks })
+}
+
+/// Turns each task's `target_date` into a `target_datetime` due at the end of that day.
+/// Tasks whose date doesn't parse are left alone and end up skipped like any broken entry.
+fn migrate_v2_to_v3(mut state: Value) -> Value {
+    let tasks = state
+        .get_mut("items")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut);
+    for task in tasks {
+        let deadline = task
+            .get("target_date")
+            .and_then(Value::as_str)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(|date| date.and_time(END_OF_DAY));
+        if let Some(deadline) = deadline {
+            task.remove("target_date");
+            task.insert(
+                "target_datetime".to_string(),
+                Value::from(deadline.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            );
+        }
+    }
+    state
+}
+
+/// Text of a todo or archive file. Every writer goes through here so they all honour
+/// `Settings::compact_json`; reading accepts either layout.
+pub fn serialize_state(state: &AppState, format: StorageFormat) -> Result<String, String> {
+    match format {
+        StorageFormat::Json if state.settings.compact_json => {
+            serde_json::to_string(state).map_err(|err| err.to_string())
+        }
+        StorageFormat::Json => serde_json::to_string_pretty(state).map_err(|err| err.to_string()),
+        StorageFormat::Toml => {
+            let mut value = serde_json::to_value(state).map_err(|err| err.to_string())?;
+            // TOML has no null; a missing key deserializes to the same `None`
+            strip_nulls(&mut value);
+            if let Some(object) = value.as_object_mut()
+                && let Some(items) = object.remove("items")
+            {
+                object.insert(TOML_TASKS_KEY.to_string(), items);
+            }
+            toml::to_string(&value).map_err(|err| err.to_string())
+        }
+    }
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|_, v| !v.is_null());
+            object.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// `todos.json` -> `todos.json.bak.<n>`, where 1 is the most recent backup
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", n));
+    path.with_file_name(name)
+}
+
+/// Shifts `.bak.1`..`.bak.<limit - 1>` down by one, dropping the oldest, and copies the
+/// current save file into `.bak.1`. A limit of 0 disables backups.
+pub fn rotate_backups(path: &Path, limit: usize) -> io::Result<()> {
+    if limit == 0 || !path.exists() {
+        return Ok(());
+    }
+    for n in (1..limit).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// `todos.json` -> `todos.json.corrupt-20250101-120000`
+pub fn corrupt_backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", Local::now().format("%Y%m%d-%H%M%S")));
+    path.with_file_name(name)
+}
+
+/// Most recent copy of `path` made by `corrupt_backup_path`, if there is one
+pub fn latest_corrupt_backup(path: &Path) -> Option<PathBuf> {
+    let mut prefix = path.file_name()?.to_os_string();
+    prefix.push(".corrupt-");
+    let prefix = prefix.to_string_lossy().into_owned();
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    // The timestamp in the name sorts chronologically
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .max()
+}
+
+/// Every `.bak.<n>` and `.corrupt-*` copy of `path` on disk, in no particular order
+pub fn copies(path: &Path) -> Vec<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let prefixes = [format!("{}.bak.", name), format!("{}.corrupt-", name)];
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            prefixes.iter().any(|prefix| name.starts_with(prefix.as_str()))
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Todo files (`*.json` and `*.toml`) in `dir`, sorted by name. Each one is a project.
+pub fn list_projects(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_todo_file = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("toml")
+            });
+        if is_todo_file && !is_archive(&path) && path.is_file() {
+            projects.push(path);
+        }
+    }
+    projects.sort();
+    Ok(projects)
+}
+
+/// `~/.local/share/rtodo/work.json` -> `work`
+pub fn project_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `todos.json` -> `todos.archive.json`, where archived tasks are kept in the same format
+pub fn archive_path(path: &Path) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".archive");
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    path.with_file_name(name)
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| Path::new(stem).extension())
+        .is_some_and(|ext| ext == "archive")
+}
+
+/// `todos.json` -> `todos.json.conflict`
+pub fn conflict_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".conflict");
+    path.with_file_name(name)
+}
+
+/// Fingerprint of the save file's content, used to notice when something else rewrote it
+pub fn content_hash(content: impl AsRef<[u8]>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.as_ref().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Writes `contents` to a sibling `.tmp` file, syncs it and renames it over `path`, so a crash
+/// mid-write never leaves a truncated save file behind.
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    let result = result.and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn failed_write_leaves_the_original_untouched() {
+        let dir = TempDir::new("atomic-write-fails");
+        let path = dir.join("todos.json");
+        fs::write(&path, "original").unwrap();
+        // A directory where the temp file should go makes creating it fail
+        fs::create_dir(dir.join("todos.json.tmp")).unwrap();
+
+        assert!(write_atomically(&path, "replacement").is_err());
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+    }
+
+    #[test]
+    fn failed_rename_leaves_the_original_untouched_and_cleans_up() {
+        let dir = TempDir::new("atomic-rename-fails");
+        // A non-empty directory can't be renamed over
+        let path = dir.join("todos.json");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("keep"), "original").unwrap();
+
+        assert!(write_atomically(&path, "replacement").is_err());
+
+        assert_eq!(fs::read_to_string(path.join("keep")).unwrap(), "original");
+        assert!(!dir.join("todos.json.tmp").exists());
+    }
+
+    #[test]
+    fn write_replaces_the_content() {
+        let dir = TempDir::new("atomic-write");
+        let path = dir.join("todos.json");
+        fs::write(&path, "original").unwrap();
+
+        write_atomically(&path, "replacement").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "replacement");
+        assert!(!dir.join("todos.json.tmp").exists());
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_backup_first_and_drops_the_oldest() {
+        let dir = TempDir::new("rotate-backups");
+        let path = dir.join("todos.json");
+        for version in 1..=4 {
+            fs::write(&path, format!("version {}", version)).unwrap();
+            rotate_backups(&path, 3).unwrap();
+        }
+
+        let backup = |n| fs::read_to_string(backup_path(&path, n)).unwrap();
+        assert_eq!(backup(1), "version 4");
+        assert_eq!(backup(2), "version 3");
+        assert_eq!(backup(3), "version 2");
+        assert!(!backup_path(&path, 4).exists());
+    }
+
+    #[test]
+    fn rotation_with_a_limit_of_zero_keeps_no_backups() {
+        let dir = TempDir::new("rotate-disabled");
+        let path = dir.join("todos.json");
+        fs::write(&path, "version 1").unwrap();
+
+        rotate_backups(&path, 0).unwrap();
+
+        assert!(!backup_path(&path, 1).exists());
+    }
+
+    /// The tasks in a save file, decoded and written back as the current version writes them
+    fn decoded_items(content: &str) -> Vec<Value> {
+        let state = parse_state(content, StorageFormat::Json)
+            .unwrap_or_else(|err| panic!("fixture failed to load: {}", err));
+        state
+            .items
+            .into_iter()
+            .map(|item| {
+                let item: crate::TodoItem = serde_json::from_value(item).expect("task decodes");
+                serde_json::to_value(item).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_historical_format_loads_to_the_same_tasks() {
+        let current = decoded_items(include_str!("../tests/fixtures/schema_v3.json"));
+        assert_eq!(current.len(), 2);
+        assert_eq!(current[0]["target_datetime"], "2025-03-01T23:59:00");
+
+        for fixture in [
+            include_str!("../tests/fixtures/schema_v1.json"),
+            include_str!("../tests/fixtures/schema_v2_unversioned.json"),
+            include_str!("../tests/fixtures/schema_v2.json"),
+        ] {
+            assert_eq!(decoded_items(fixture), current);
+        }
+    }
+
+    #[test]
+    fn migrated_files_keep_their_settings() {
+        let state = parse_state(include_str!("../tests/fixtures/schema_v2.json"), StorageFormat::Json)
+            .unwrap_or_else(|err| panic!("fixture failed to load: {}", err));
+
+        assert_eq!(state.version, CURRENT_VERSION);
+        assert!(state.settings.sort_mode == SortMode::TargetDate);
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let content = format!("{{\"version\": {}, \"items\": []}}", CURRENT_VERSION + 1);
+
+        assert!(matches!(
+            parse_state(&content, StorageFormat::Json),
+            Err(ParseError::UnsupportedVersion(version)) if version == CURRENT_VERSION + 1
+        ));
+    }
+}
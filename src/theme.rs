@@ -0,0 +1,244 @@
+//! Colors the interface is drawn in, read from `~/.config/rtodo/theme.toml` (or the
+//! platform's equivalent) or picked from the built-in themes with `--theme`.
+
+use serde::{Deserialize, Deserializer};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use directories::ProjectDirs;
+use ratatui::style::Color;
+
+/// A color for each role in the interface. In `theme.toml` a color is a name (`red`,
+/// `light-blue`), a 256-color index (`208`) or `#rrggbb`; roles left out keep the default.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Done tasks, info messages and a full progress bar
+    #[serde(deserialize_with = "color")]
+    pub completed_fg: Color,
+    /// Tasks up to `SEVERELY_OVERDUE_DAYS` late
+    #[serde(deserialize_with = "color")]
+    pub overdue_fg: Color,
+    /// Tasks later than that
+    #[serde(deserialize_with = "color")]
+    pub severe_overdue_fg: Color,
+    /// Open tasks due within the configured warning window
+    #[serde(deserialize_with = "color")]
+    pub due_soon_fg: Color,
+    /// Open tasks of Critical priority
+    #[serde(deserialize_with = "color")]
+    pub critical_fg: Color,
+    /// Every other open task, and plain text
+    #[serde(deserialize_with = "color")]
+    pub pending_fg: Color,
+    /// Background of rows marked for a bulk action
+    #[serde(deserialize_with = "color")]
+    pub selected_bg: Color,
+    /// Table headers and screen titles
+    #[serde(deserialize_with = "color")]
+    pub header_fg: Color,
+    /// Labels, popup borders and the focused form field
+    #[serde(deserialize_with = "color")]
+    pub accent_fg: Color,
+    /// Errors, warnings and the overdue count
+    #[serde(deserialize_with = "color")]
+    pub error_fg: Color,
+    /// Placeholders and empty parts of bars
+    #[serde(deserialize_with = "color")]
+    pub muted_fg: Color,
+    /// Key hints under popups and in the footer
+    #[serde(deserialize_with = "color")]
+    pub hint_fg: Color,
+    /// Keys in the help overlay
+    #[serde(deserialize_with = "color")]
+    pub key_fg: Color,
+    /// Behind popups, and text drawn on an accent color
+    #[serde(deserialize_with = "color")]
+    pub background: Color,
+    /// Progress bar below a third, up to two thirds and above two thirds done
+    #[serde(deserialize_with = "color")]
+    pub progress_low_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub progress_mid_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub progress_high_fg: Color,
+    /// The dot beside each priority, from Low to Critical
+    #[serde(deserialize_with = "color")]
+    pub priority_low_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub priority_medium_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub priority_high_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub priority_critical_fg: Color,
+    /// Colors handed out to tags, so the same tag always gets the same color
+    #[serde(deserialize_with = "tag_colors")]
+    pub tag_colors: [Color; TAG_COLOR_COUNT],
+}
+
+/// How many colors `tag_colors` holds
+pub const TAG_COLOR_COUNT: usize = 6;
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            completed_fg: Color::Green,
+            // Orange, from the 256-color palette
+            overdue_fg: Color::Indexed(208),
+            severe_overdue_fg: Color::LightRed,
+            due_soon_fg: Color::Yellow,
+            critical_fg: Color::Red,
+            pending_fg: Color::White,
+            selected_bg: Color::DarkGray,
+            header_fg: Color::Yellow,
+            accent_fg: Color::Yellow,
+            error_fg: Color::Red,
+            muted_fg: Color::DarkGray,
+            hint_fg: Color::Gray,
+            key_fg: Color::Cyan,
+            background: Color::Black,
+            progress_low_fg: Color::Red,
+            progress_mid_fg: Color::Yellow,
+            progress_high_fg: Color::Green,
+            priority_low_fg: Color::Blue,
+            priority_medium_fg: Color::Yellow,
+            priority_high_fg: Color::Red,
+            priority_critical_fg: Color::Magenta,
+            tag_colors: [
+                Color::Cyan,
+                Color::Magenta,
+                Color::LightBlue,
+                Color::LightGreen,
+                Color::LightYellow,
+                Color::LightMagenta,
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// Ethan Schoonover's Solarized (dark), in true color
+    fn solarized() -> Self {
+        const BASE03: Color = Color::Rgb(0x00, 0x2b, 0x36);
+        const BASE02: Color = Color::Rgb(0x07, 0x36, 0x42);
+        const BASE01: Color = Color::Rgb(0x58, 0x6e, 0x75);
+        const BASE0: Color = Color::Rgb(0x83, 0x94, 0x96);
+        const BASE1: Color = Color::Rgb(0x93, 0xa1, 0xa1);
+        const YELLOW: Color = Color::Rgb(0xb5, 0x89, 0x00);
+        const ORANGE: Color = Color::Rgb(0xcb, 0x4b, 0x16);
+        const RED: Color = Color::Rgb(0xdc, 0x32, 0x2f);
+        const MAGENTA: Color = Color::Rgb(0xd3, 0x36, 0x82);
+        const VIOLET: Color = Color::Rgb(0x6c, 0x71, 0xc4);
+        const BLUE: Color = Color::Rgb(0x26, 0x8b, 0xd2);
+        const CYAN: Color = Color::Rgb(0x2a, 0xa1, 0x98);
+        const GREEN: Color = Color::Rgb(0x85, 0x99, 0x00);
+        Self {
+            completed_fg: GREEN,
+            overdue_fg: ORANGE,
+            severe_overdue_fg: RED,
+            due_soon_fg: YELLOW,
+            critical_fg: MAGENTA,
+            pending_fg: BASE0,
+            selected_bg: BASE02,
+            header_fg: BLUE,
+            accent_fg: YELLOW,
+            error_fg: RED,
+            muted_fg: BASE01,
+            hint_fg: BASE1,
+            key_fg: CYAN,
+            background: BASE03,
+            progress_low_fg: RED,
+            progress_mid_fg: YELLOW,
+            progress_high_fg: GREEN,
+            priority_low_fg: BLUE,
+            priority_medium_fg: YELLOW,
+            priority_high_fg: RED,
+            priority_critical_fg: MAGENTA,
+            tag_colors: [CYAN, VIOLET, BLUE, GREEN, YELLOW, MAGENTA],
+        }
+    }
+}
+
+/// Built-in themes, picked with `--theme` instead of reading `theme.toml`
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ThemeName {
+    Default,
+    Solarized,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Default => Theme::default(),
+            ThemeName::Solarized => Theme::solarized(),
+        }
+    }
+}
+
+pub fn theme_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rtodo").map(|dirs| dirs.config_dir().join("theme.toml"))
+}
+
+/// The theme at `path`, or the default theme when there is no such file
+pub fn load(path: &Path) -> Result<Theme, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => toml::from_str(&content).map_err(|err| err.to_string()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Theme::default()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name).map_err(|_| serde::de::Error::custom(format!("unknown color `{}`", name)))
+}
+
+fn tag_colors<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[Color; TAG_COLOR_COUNT], D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    let count = names.len();
+    let colors = names
+        .into_iter()
+        .map(|name| {
+            Color::from_str(&name)
+                .map_err(|_| serde::de::Error::custom(format!("unknown color `{}`", name)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    colors.try_into().map_err(|_| {
+        serde::de::Error::custom(format!("expected {} tag colors, got {}", TAG_COLOR_COUNT, count))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_and_tag_colors_are_read_from_toml() {
+        let theme: Theme = toml::from_str(
+            r##"
+            priority_critical_fg = "#ff0000"
+            tag_colors = ["red", "green", "blue", "1", "2", "#000000"]
+            "##,
+        )
+        .unwrap_or_else(|err| panic!("{}", err));
+
+        assert_eq!(theme.priority_critical_fg, Color::Rgb(0xff, 0, 0));
+        assert_eq!(theme.priority_low_fg, Theme::default().priority_low_fg);
+        assert_eq!(theme.tag_colors[1], Color::Green);
+        assert_eq!(theme.tag_colors[5], Color::Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn tag_colors_must_have_six_entries() {
+        let Err(err) = toml::from_str::<Theme>(r#"tag_colors = ["red", "green"]"#) else {
+            panic!("two tag colors were accepted");
+        };
+        assert!(err.to_string().contains("expected 6 tag colors, got 2"), "{}", err);
+
+        assert!(toml::from_str::<Theme>(r#"tag_colors = ["red", "nope"]"#).is_err());
+    }
+}
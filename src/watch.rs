@@ -0,0 +1,50 @@
+//! Notices when the save file is rewritten by another program.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+
+pub struct SaveFileWatcher {
+    // Kept alive for as long as events should keep arriving
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl SaveFileWatcher {
+    /// Watches the directory rather than the file itself, since atomic saves replace the
+    /// file with a new one that a file watch would lose track of
+    pub fn new(save_path: &Path) -> notify::Result<Self> {
+        let dir = match save_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Whether `save_path` was touched since the last call. It may be any file in the
+    /// watched directory, so switching projects keeps working. Our own saves show up here
+    /// too; the caller tells them apart by content.
+    pub fn has_changed(&self, save_path: &Path) -> bool {
+        let file_name = save_path.file_name();
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            changed |= event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == file_name);
+        }
+        changed
+    }
+}